@@ -2,11 +2,13 @@
 #![feature(test)]  
 extern crate test;
 
+use futures_util::StreamExt;
 use simple_logger::SimpleLogger;
 
 mod std_lib;
 mod tokio_lib;
 mod rpc;
+mod programs;
 mod concurrency_vs_parallelism;
 
 // by default, it's set to the "multi_thread" runtime && default worker threads == available CPU cores,
@@ -20,18 +22,32 @@ async fn main() {
     // tokio_lib::channels::basics().await;
 
     let sig: &str = "yn5n7ke3b59mVaUTJVKb6gA9C5xr2jVeqadKk1vUDMjAX6LrCYi365D8qrRsDLC3TwGpCPvnt24w37fqL3mLevP";
-    rpc::communication::get_transaction(
+    let tx = rpc::communication::get_transaction(
         "https://api.mainnet-beta.solana.com",  // your_rpc_provider_http_url 
         sig, 
-        rpc::communication::CommitmentLevel::Confirmed
+        rpc::config::TransactionConfig { commitment: Some(rpc::config::TxCommitment::Confirmed), ..Default::default() }
     ).await.unwrap();
+    log::info!("{:#?}", tx);
+
+    // same request, but through the pooled client, which should be built once and shared (cheap to clone)
+    let client = rpc::client::RpcClient::new("https://api.mainnet-beta.solana.com").unwrap();
+    let slot: u64 = client.get_slot().await.unwrap();
+    let tx = client.get_transaction(sig).await.unwrap();
+    log::info!("slot: {slot}, tx found: {}", tx.is_some());
 
     let account_id: &str = "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4";
-    rpc::communication::account_subscribe(
+    let mut subscription = rpc::communication::account_subscribe(
         "wss://api.mainnet-beta.solana.com",  // your rpc_provider_ws_url 
         account_id, 
-        rpc::communication::CommitmentLevel::Confirmed
+        rpc::config::AccountInfoConfig { commitment: Some(rpc::communication::CommitmentLevel::Confirmed), ..Default::default() }
     ).await.unwrap();
+
+    while let Some(notification) = subscription.next().await {
+        match notification {
+            Ok(n) => log::info!("slot: {}, lamports: {}", n.slot, n.account.lamports),
+            Err(e) => log::error!("{e}"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -45,6 +61,1333 @@ mod tests {
         concurrency_vs_parallelism::try_example().unwrap();
     }
 
+    #[test]
+    fn get_transaction_response_deserializes() {
+        use rpc::types::{RpcResponse, EncodedTransactionWithMeta, TransactionVersion};
+
+        let body: &str = r#"{"jsonrpc":"2.0","id":1,"result":{
+            "slot":327147000,"blockTime":1742000000,"version":0,
+            "meta":{"err":null,"fee":5000,"preBalances":[1000000,1],"postBalances":[995000,1],
+                "innerInstructions":[],"logMessages":["Program 11111111111111111111111111111111 invoke [1]","Program 11111111111111111111111111111111 success"],
+                "preTokenBalances":[],"postTokenBalances":[],"rewards":[],
+                "loadedAddresses":{"writable":[],"readonly":[]},"computeUnitsConsumed":150},
+            "transaction":{"signatures":["yn5n7ke3b59mVaUTJVKb6gA9C5xr2jVeqadKk1vUDMjAX6LrCYi365D8qrRsDLC3TwGpCPvnt24w37fqL3mLevP"],
+                "message":{"header":{"numRequiredSignatures":1,"numReadonlySignedAccounts":0,"numReadonlyUnsignedAccounts":1},
+                    "accountKeys":["3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4","11111111111111111111111111111111"],
+                    "recentBlockhash":"EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N",
+                    "instructions":[{"programIdIndex":1,"accounts":[0],"data":"3Bxs4h24hBtQy9rw","stackHeight":null}],
+                    "addressTableLookups":[]}}}}"#;
+
+        let res: RpcResponse<EncodedTransactionWithMeta> = serde_json::from_str(body).unwrap();
+        let tx: EncodedTransactionWithMeta = res.result.unwrap();
+        assert_eq!(tx.slot, 327147000);
+        assert_eq!(tx.version, Some(TransactionVersion::Number(0)));
+        let meta = tx.meta.unwrap();
+        assert_eq!(meta.fee, 5000);
+        assert_eq!(meta.log_messages.unwrap().len(), 2);
+        assert_eq!(tx.transaction.decode().unwrap().message.instructions[0].program_id_index, 1);
+
+        // not found && JSON-RPC error
+        let res: RpcResponse<EncodedTransactionWithMeta> = serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":null}"#).unwrap();
+        assert!(res.result.is_none() && res.error.is_none());
+        let res: RpcResponse<EncodedTransactionWithMeta> = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"Invalid param: WrongSize"}}"#
+        ).unwrap();
+        assert_eq!(res.error.unwrap().code, -32602);
+    }
+
+    #[test]
+    fn rpc_error_retryability() {
+        use rpc::{error::{RpcError, codes}, types::RpcResponseError};
+
+        let json_rpc = |code: i64| RpcError::JsonRpc(RpcResponseError { code, message: String::new(), data: None });
+        assert!(json_rpc(codes::NODE_UNHEALTHY).is_retryable());
+        assert!(!json_rpc(codes::SLOT_SKIPPED).is_retryable());
+        assert_eq!(json_rpc(codes::BLOCK_NOT_AVAILABLE).code(), Some(-32004));
+
+        assert!(RpcError::HttpStatus { status: 429, retry_after: None, body: String::new() }.is_retryable());
+        assert!(!RpcError::HttpStatus { status: 401, retry_after: None, body: String::new() }.is_retryable());
+        assert!(RpcError::from(tokio_tungstenite::tungstenite::Error::ConnectionClosed).is_retryable());
+        assert!(!RpcError::InvalidRequest("processed".into()).is_retryable());
+    }
+
+    #[test]
+    fn reconnect_backoff_is_bounded() {
+        use std::time::Duration;
+        use rpc::subscription::ReconnectPolicy;
+
+        let policy = ReconnectPolicy { initial_backoff: Duration::from_millis(100), max_backoff: Duration::from_secs(1), max_attempts: None };
+        for _ in 0..100 {
+            let first: Duration = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third: Duration = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            assert!(policy.backoff(u32::MAX) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn pubsub_filters_serialize() {
+        use rpc::pubsub::{LogsFilter, ProgramFilter, Memcmp, MemcmpEncoding, BlockFilter};
+
+        assert_eq!(serde_json::to_value(LogsFilter::AllWithVotes).unwrap(), serde_json::json!("allWithVotes"));
+        assert_eq!(
+            serde_json::to_value(LogsFilter::Mentions(vec!["wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5".into()])).unwrap(),
+            serde_json::json!({ "mentions": ["wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5"] })
+        );
+        assert_eq!(
+            serde_json::to_value(vec![
+                ProgramFilter::DataSize(24),
+                ProgramFilter::Memcmp(Memcmp { offset: 0, bytes: "AQID".into(), encoding: Some(MemcmpEncoding::Base64) }),
+            ]).unwrap(),
+            serde_json::json!([{ "dataSize": 24 }, { "memcmp": { "offset": 0, "bytes": "AQID", "encoding": "base64" } }])
+        );
+        assert_eq!(serde_json::to_value(BlockFilter::All).unwrap(), serde_json::json!("all"));
+    }
+
+    #[test]
+    fn binary_encodings_decode_to_same_bytes() {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use rpc::{encoding::UiEncoding, types::{EncodedTransaction, UiAccountData}};
+
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = [
+            UiAccountData::Binary(bs58::encode(&data).into_string(), UiEncoding::Base58),
+            UiAccountData::Binary(BASE64.encode(&data), UiEncoding::Base64),
+            UiAccountData::Binary(BASE64.encode(zstd::encode_all(data.as_slice(), 3).unwrap()), UiEncoding::Base64Zstd),
+            UiAccountData::LegacyBinary(bs58::encode(&data).into_string()),
+        ];
+        for account_data in encoded {
+            assert_eq!(account_data.decode().unwrap(), data);
+        }
+        assert!(UiAccountData::Json(serde_json::json!({ "parsed": {} })).decode().is_err());
+
+        // v0 transaction: 1 signature, 2 static keys, 1 instruction, 1 lookup table
+        let mut wire: Vec<u8> = vec![1];
+        wire.extend([7; 64]);
+        wire.extend([0x80, 1, 0, 1]);
+        wire.push(2);
+        wire.extend([1; 32]);
+        wire.extend([0; 32]);
+        wire.extend([9; 32]);
+        wire.extend([1, 1, 1, 0, 3, 2, 0, 0]);
+        wire.push(1);
+        wire.extend([5; 32]);
+        wire.extend([1, 0, 0]);
+
+        let tx = EncodedTransaction::Binary(BASE64.encode(&wire), UiEncoding::Base64).decode().unwrap();
+        assert_eq!(tx.signatures, vec![bs58::encode([7; 64]).into_string()]);
+        assert_eq!(tx.message.header.num_readonly_unsigned_accounts, 1);
+        assert_eq!(tx.message.account_keys[1], "11111111111111111111111111111111");
+        assert_eq!(tx.message.instructions[0].accounts, vec![0]);
+        assert_eq!(tx.message.instructions[0].data, bs58::encode([2, 0, 0]).into_string());
+        let lookups = tx.message.address_table_lookups.unwrap();
+        assert_eq!((lookups[0].writable_indexes.clone(), lookups[0].readonly_indexes.clone()), (vec![0], vec![]));
+
+        // same bytes as base58, truncated => error
+        assert_eq!(EncodedTransaction::LegacyBinary(bs58::encode(&wire).into_string()).decode().unwrap().signatures, tx.signatures);
+        assert!(EncodedTransaction::Binary(BASE64.encode(&wire[..100]), UiEncoding::Base64).decode().is_err());
+    }
+
+    #[test]
+    fn logs_rebuild_call_tree() {
+        use rpc::logs::{parse_logs, ComputeUnits, InvocationResult};
+
+        const PROGRAM: &str = "wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5";
+        const SYSTEM: &str = "11111111111111111111111111111111";
+
+        let init_pda = [
+            format!("Program {PROGRAM} invoke [1]"),
+            "Program log: Instruction: InitPda".into(),
+            format!("Program {SYSTEM} invoke [2]"),
+            format!("Program {SYSTEM} success"),
+            format!("Program {PROGRAM} consumed 6012 of 200000 compute units"),
+            format!("Program {PROGRAM} success"),
+        ];
+        let parsed = parse_logs(&init_pda).unwrap();
+        assert!(parsed.succeeded() && !parsed.truncated);
+        let invocation = &parsed.invocations[0];
+        assert_eq!(invocation.instruction.as_deref(), Some("InitPda"));
+        assert_eq!(invocation.compute_units, Some(ComputeUnits { consumed: 6012, limit: 200000 }));
+        assert_eq!((invocation.inner[0].program_id.as_str(), invocation.inner[0].depth), (SYSTEM, 2));
+        assert_eq!(parsed.invocations_of(SYSTEM).count(), 1);
+
+        let update_pda = [
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]".into(),
+            "Program ComputeBudget111111111111111111111111111111 success".into(),
+            format!("Program {PROGRAM} invoke [1]"),
+            "Program log: Instruction: UpdatePda".into(),
+            "Program log: counter state: 2".into(),
+            "Program data: AQID BAU=".into(),
+            format!("Program {PROGRAM} consumed 3000 of 199850 compute units"),
+            format!("Program {PROGRAM} failed: custom program error: 0x1771"),
+        ];
+        let parsed = parse_logs(&update_pda).unwrap();
+        assert!(!parsed.succeeded());
+        let invocation = parsed.invocations_of(PROGRAM).next().unwrap();
+        assert_eq!(invocation.logs, vec!["counter state: 2"]);
+        assert_eq!(invocation.data, vec![vec![1, 2, 3], vec![4, 5]]);
+        assert_eq!(invocation.result, InvocationResult::Failed("custom program error: 0x1771".into()));
+
+        // cut off in the middle of a CPI
+        let parsed = parse_logs(&[&init_pda[0], &init_pda[1], &init_pda[2], "Log truncated"]).unwrap();
+        assert!(parsed.truncated);
+        assert_eq!(parsed.invocations[0].result, InvocationResult::Incomplete);
+        assert_eq!(parsed.invocations[0].inner[0].result, InvocationResult::Incomplete);
+
+        // success of a program that never started
+        assert!(parse_logs(&[format!("Program {SYSTEM} success")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn keepalive_detects_missing_pong() {
+        use std::time::Duration;
+        use rpc::{error::RpcError, keepalive::{Keepalive, KeepaliveAction, KeepaliveConfig}};
+
+        let config = KeepaliveConfig { ping_interval: Duration::from_millis(20), pong_timeout: Duration::from_millis(30), idle_timeout: None };
+        let mut keepalive = Keepalive::new(config);
+
+        // answered Ping keeps the connection alive
+        assert!(matches!(keepalive.tick().await, KeepaliveAction::Ping));
+        keepalive.on_pong();
+        assert!(matches!(keepalive.tick().await, KeepaliveAction::Ping));
+
+        // unanswered one kills it
+        assert!(matches!(keepalive.tick().await, KeepaliveAction::Ping));
+        assert!(matches!(keepalive.tick().await, KeepaliveAction::Dead(RpcError::PongTimeout(_))));
+    }
+
+    // ---- offline integration tests against the in-process mock node (rpc::mock) ----
+
+    #[tokio::test]
+    async fn get_transaction_against_mock() {
+        use rpc::{
+            client::RpcClient,
+            communication::{self, CommitmentLevel},
+            config::{TransactionConfig, TxCommitment},
+            encoding::UiEncoding,
+            error::RpcError,
+            mock::{MockServer, MockResponse},
+        };
+
+        let server = MockServer::start().await;
+        server
+            .on("getTransaction", MockResponse::Result(serde_json::Value::Null))
+            .on("getTransaction", MockResponse::Error { code: -32602, message: "Invalid param: WrongSize".into() });
+
+        let tx = communication::get_transaction(server.http_url(), "unknown", TransactionConfig { commitment: Some(TxCommitment::Confirmed), encoding: Some(UiEncoding::Json), ..Default::default() }).await.unwrap();
+        assert!(tx.is_none());
+
+        let e = communication::get_transaction(server.http_url(), "bad", TransactionConfig { commitment: Some(TxCommitment::Finalized), encoding: Some(UiEncoding::Base64), ..Default::default() }).await.unwrap_err();
+        assert!(matches!(e, RpcError::JsonRpc(ref e) if e.code == -32602));
+        assert_eq!(server.requests_of("getTransaction"), 2);
+
+        // processed can't even be passed to getTransaction, the client's processed default is raised to confirmed
+        let client = RpcClient::new_with_commitment(server.http_url(), CommitmentLevel::Processed).unwrap();
+        let _ = client.get_transaction("bad").await;
+        let requests = server.requests();
+        assert_eq!(requests.last().unwrap()["params"][1]["commitment"], "confirmed");
+        assert_eq!(requests[1]["params"][1]["commitment"], "finalized");
+
+        // unset options are omitted, not sent as null
+        let config = TransactionConfig { max_supported_transaction_version: None, min_context_slot: Some(7), ..Default::default() };
+        let _ = client.get_transaction_with_config("bad", config).await;
+        assert_eq!(server.requests().last().unwrap()["params"][1], serde_json::json!({
+            "encoding": "json", "commitment": "confirmed", "minContextSlot": 7
+        }));
+    }
+
+    #[tokio::test]
+    async fn get_account_decodes_zstd_data() {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use rpc::{client::RpcClient, config::AccountInfoConfig, encoding::UiEncoding, mock::{MockServer, MockResponse, account_value, with_context}};
+
+        let data: Vec<u8> = [0u8; 24].iter().chain(b"meta").copied().collect();
+        let mut account = account_value(1_000, "");
+        account["data"] = serde_json::json!([BASE64.encode(zstd::encode_all(data.as_slice(), 3).unwrap()), "base64+zstd"]);
+
+        let server = MockServer::start().await;
+        server.on("getAccountInfo", MockResponse::Result(with_context(7, account)));
+
+        let client = RpcClient::new(server.http_url()).unwrap();
+        let config = AccountInfoConfig { encoding: Some(UiEncoding::Base64Zstd), ..Default::default() };
+        let account = client.get_account_with_config("3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", config).await.unwrap().unwrap();
+        assert_eq!(account.data, data);
+        assert_eq!(server.requests()[0]["params"][1]["encoding"], "base64+zstd");
+    }
+
+    #[tokio::test]
+    async fn batch_matches_out_of_order_responses() {
+        use rpc::{
+            client::RpcClient,
+            config::{ContextConfig, TransactionConfig},
+            mock::{MockServer, MockResponse, with_context},
+        };
+
+        let server = MockServer::start().await;
+        server
+            .on("getSlot", MockResponse::Result(serde_json::json!(42)))
+            .on("getBalance", MockResponse::Result(with_context(42, serde_json::json!(1_000))))
+            .on("getTransaction", MockResponse::Error { code: -32009, message: "Slot skipped".into() });
+
+        let client = RpcClient::new(server.http_url()).unwrap();
+        let mut batch = client.batch();
+        let balance = batch.get_balance("3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", ContextConfig::default());
+        let tx = batch.get_transaction("sig", TransactionConfig::default());
+        let slot = batch.get_slot(ContextConfig { min_context_slot: Some(40), ..Default::default() });
+        assert_eq!(batch.len(), 3);
+
+        let mut res = batch.send().await.unwrap();
+        assert_eq!(res.take(slot).unwrap(), 42);
+        assert_eq!(res.take(balance).unwrap(), 1_000);
+        assert_eq!(res.take(tx).unwrap_err().code(), Some(-32009));
+        assert_eq!(server.requests().len(), 3);  // single POST, but 3 requests
+        let get_slot = server.requests().into_iter().find(|req| req["method"] == "getSlot").unwrap();
+        assert_eq!(get_slot["params"], serde_json::json!([{ "commitment": "confirmed", "minContextSlot": 40 }]));
+    }
+
+    #[tokio::test]
+    async fn retry_policy_honours_429_and_skips_fatal_errors() {
+        use std::time::Duration;
+        use rpc::{client::RpcClient, error::RpcError, mock::{MockServer, MockResponse}, retry::{ExponentialBackoff, RetryPolicy}};
+
+        let server = MockServer::start().await;
+        server
+            .on("getSlot", MockResponse::HttpStatus { status: 429, retry_after: Some(0) })
+            .on("getSlot", MockResponse::Error { code: -32005, message: "Node is unhealthy".into() })
+            .on("getSlot", MockResponse::Result(serde_json::json!(42)))
+            .on("getBalance", MockResponse::Error { code: -32602, message: "Invalid param".into() });
+
+        let policy = ExponentialBackoff { max_attempts: 3, initial_backoff: Duration::from_millis(5), max_backoff: Duration::from_millis(20) };
+        // both forms of Retry-After, 784111777 == Sun, 06 Nov 1994 08:49:37 GMT
+        let now = std::time::UNIX_EPOCH + Duration::from_secs(784_111_777 - 10);
+        assert_eq!(rpc::client::parse_retry_after(" 3 ", now), Some(Duration::from_secs(3)));
+        assert_eq!(rpc::client::parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now), Some(Duration::from_secs(10)));
+        assert_eq!(rpc::client::parse_retry_after("Sat, 05 Nov 1994 08:49:37 GMT", now), Some(Duration::ZERO));
+        assert_eq!(rpc::client::parse_retry_after("soon", now), None);
+
+        // an hour long Retry-After is capped
+        let throttled = RpcError::HttpStatus { status: 429, retry_after: Some(Duration::from_secs(3600)), body: String::new() };
+        assert_eq!(policy.next_delay(1, &throttled), Some(Duration::from_millis(20)));
+        let client = RpcClient::new(server.http_url()).unwrap().with_retry_policy(policy);
+
+        assert_eq!(client.get_slot().await.unwrap(), 42);
+        assert_eq!(server.requests_of("getSlot"), 3);
+
+        // not transient => no retries
+        assert!(matches!(client.get_balance("A").await, Err(RpcError::JsonRpc(_))));
+        assert_eq!(server.requests_of("getBalance"), 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_spreads_requests() {
+        use std::time::{Duration, Instant};
+        use rpc::retry::RateLimiter;
+
+        let limiter = RateLimiter::new(20.0, 2);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+        // 2 tokens of burst are free, the other 2 cost 50ms each
+        assert!(start.elapsed() >= Duration::from_millis(90));
+
+        assert!(std::panic::catch_unwind(|| RateLimiter::new(0.0, 1)).is_err());
+        assert!(std::panic::catch_unwind(|| RateLimiter::new(f64::NAN, 1)).is_err());
+
+        // callers, that gave up waiting, don't keep their tokens
+        let limiter = RateLimiter::new(10.0, 1);
+        limiter.acquire().await;
+        for _ in 0..5 {
+            assert!(tokio::time::timeout(Duration::from_millis(1), limiter.acquire()).await.is_err());
+        }
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn failover_leaves_lagging_endpoint() {
+        use std::time::Duration;
+        use rpc::{
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            failover::{Endpoint, FailoverClient, FailoverConfig},
+            keepalive::KeepaliveConfig,
+            mock::{MockServer, MockResponse, WsStep, notification, account_value, with_context},
+            subscription::ReconnectPolicy,
+        };
+
+        let primary = MockServer::start().await;
+        primary
+            .on("getHealth", MockResponse::Result(serde_json::json!("ok")))
+            .on("getSlot", MockResponse::Result(serde_json::json!(2_000)))
+            .on("getSlot", MockResponse::Result(serde_json::json!(1_000)))
+            .on("getBalance", MockResponse::Result(with_context(1_000, serde_json::json!(5))))
+            .push_ws_session(vec![
+                WsStep::Confirm(1),
+                WsStep::Notify(notification("accountNotification", 1, with_context(100, account_value(1, "AAAA")))),
+            ]);
+        let secondary = MockServer::start().await;
+        secondary
+            .on("getHealth", MockResponse::Result(serde_json::json!("ok")))
+            .on("getSlot", MockResponse::Result(serde_json::json!(2_000)))
+            .on("getBalance", MockResponse::HttpStatus { status: 503, retry_after: None })
+            .push_ws_session(vec![
+                WsStep::Confirm(7),
+                WsStep::Notify(notification("accountNotification", 7, with_context(200, account_value(2, "AAAA")))),
+            ]);
+
+        let endpoints = vec![
+            Endpoint { http_url: primary.http_url(), ws_url: primary.ws_url(), weight: 10 },
+            Endpoint { http_url: secondary.http_url(), ws_url: secondary.ws_url(), weight: 1 },
+        ];
+        let config = FailoverConfig { health_check_interval: Duration::from_millis(200), ..Default::default() };
+        let failover = FailoverClient::new(endpoints, config).unwrap();
+
+        // the heaviest endpoint is preferred for subscriptions
+        let (events_tx, _events) = tokio::sync::mpsc::unbounded_channel();
+        let policy = ReconnectPolicy { initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_millis(50), max_attempts: Some(3) };
+        let mut subscription = failover.account_subscribe(
+            "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }, policy, KeepaliveConfig::default(), events_tx
+        );
+        assert_eq!(subscription.next().await.unwrap().unwrap().slot, 100);
+
+        // the next background check finds primary 1000 slots behind => unhealthy, the subscription moves
+        assert_eq!(subscription.next().await.unwrap().unwrap().slot, 200);
+        let healthy: Vec<String> = failover.healthy_endpoints().iter().map(|e| e.http_url.clone()).collect();
+        assert_eq!(healthy, vec![secondary.http_url()]);
+        assert_eq!(secondary.requests_of("accountSubscribe"), 1);
+
+        // the only healthy endpoint answers 503 => the lagging one is still better than nothing
+        let balance: u64 = failover.call(|client| async move { client.get_balance("A").await }).await.unwrap();
+        assert_eq!(balance, 5);
+        assert_eq!(secondary.requests_of("getBalance"), 1);
+        assert_eq!(primary.requests_of("getBalance"), 1);
+    }
+
+    #[tokio::test]
+    async fn failover_leaves_unreachable_ws_endpoint() {
+        use std::time::Duration;
+        use rpc::{
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            failover::{Endpoint, FailoverClient, FailoverConfig},
+            keepalive::KeepaliveConfig,
+            mock::{MockServer, MockResponse, WsStep, notification, account_value, with_context},
+            subscription::ReconnectPolicy,
+        };
+
+        // both pass the http health checks, but nothing listens on the ws port of the primary
+        let primary = MockServer::start().await;
+        primary
+            .on("getHealth", MockResponse::Result(serde_json::json!("ok")))
+            .on("getSlot", MockResponse::Result(serde_json::json!(1_000)));
+        let secondary = MockServer::start().await;
+        secondary
+            .on("getHealth", MockResponse::Result(serde_json::json!("ok")))
+            .on("getSlot", MockResponse::Result(serde_json::json!(1_000)))
+            .push_ws_session(vec![
+                WsStep::Confirm(7),
+                WsStep::Notify(notification("accountNotification", 7, with_context(200, account_value(2, "AAAA")))),
+            ]);
+
+        let endpoints = vec![
+            Endpoint { http_url: primary.http_url(), ws_url: "ws://127.0.0.1:1".to_string(), weight: 10 },
+            Endpoint { http_url: secondary.http_url(), ws_url: secondary.ws_url(), weight: 1 },
+        ];
+        let failover = FailoverClient::new(endpoints, FailoverConfig::default()).unwrap();
+
+        // max_consecutive_errors failed connects => primary is unhealthy, the reconnects go to secondary
+        let (events_tx, _events) = tokio::sync::mpsc::unbounded_channel();
+        let policy = ReconnectPolicy { initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_millis(50), max_attempts: None };
+        let mut subscription = failover.account_subscribe(
+            "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }, policy, KeepaliveConfig::default(), events_tx
+        );
+        assert_eq!(subscription.next().await.unwrap().unwrap().slot, 200);
+        let healthy: Vec<String> = failover.healthy_endpoints().iter().map(|e| e.http_url.clone()).collect();
+        assert_eq!(healthy, vec![secondary.http_url()]);
+    }
+
+    #[tokio::test]
+    async fn backfill_pages_and_resumes_from_cursor() {
+        use rpc::{
+            backfill::{BackfillCursor, SignatureBackfill},
+            client::RpcClient,
+            mock::{MockServer, MockResponse},
+            types::SignatureInfo,
+        };
+
+        let info = |signature: &str, slot: u64| serde_json::json!({
+            "signature": signature, "slot": slot, "err": null, "memo": null, "blockTime": null, "confirmationStatus": "finalized"
+        });
+        let server = MockServer::start().await;
+        server
+            .on("getSignaturesForAddress", MockResponse::Result(serde_json::json!([info("s3", 30), info("s2", 20)])))
+            .on("getSignaturesForAddress", MockResponse::Result(serde_json::json!([info("s1", 10)])))
+            .on("getSignaturesForAddress", MockResponse::Result(serde_json::json!([])))
+            .on("getTransaction", MockResponse::Result(serde_json::Value::Null));
+
+        // the provider caps the page below the requested limit, that's not the end of the history
+        let client = RpcClient::new(server.http_url()).unwrap();
+        let mut backfill = SignatureBackfill::new(client.clone(), "wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5").page_limit(3);
+        let page = backfill.next_page_with_transactions(2).await.unwrap().unwrap();
+        assert_eq!(page.iter().map(|(info, _)| info.signature.as_str()).collect::<Vec<_>>(), vec!["s3", "s2"]);
+        assert_eq!(server.requests_of("getTransaction"), 2);
+
+        // "crash": the persisted cursor is all the new backfill gets
+        let saved: String = serde_json::to_string(backfill.cursor()).unwrap();
+        drop(backfill);
+        let cursor: BackfillCursor = serde_json::from_str(&saved).unwrap();
+        assert_eq!((cursor.before.as_deref(), cursor.newest.as_deref()), (Some("s2"), Some("s3")));
+
+        let rest: Vec<SignatureInfo> = SignatureBackfill::new(client, "wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5")
+            .page_limit(3)
+            .resume_from(cursor)
+            .into_stream()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(rest.iter().map(|info| info.slot).collect::<Vec<_>>(), vec![10]);
+
+        // only the empty page ends it
+        let pages: Vec<serde_json::Value> = server.requests().into_iter().filter(|req| req["method"] == "getSignaturesForAddress").collect();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[1]["params"][1], serde_json::json!({ "commitment": "confirmed", "before": "s2", "limit": 3 }));
+        assert_eq!(pages[2]["params"][1]["before"], "s1");
+    }
+
+    #[tokio::test]
+    async fn sender_confirms_expires_and_fails() {
+        use std::time::Duration;
+        use rpc::{
+            client::RpcClient,
+            communication::CommitmentLevel,
+            mock::{MockServer, MockResponse, WsStep, notification, with_context},
+            pubsub::PubsubClient,
+            sender::{SenderConfig, TransactionSender, TransactionStatus},
+        };
+
+        let config = SenderConfig { rebroadcast_interval: Duration::from_millis(15), poll_interval: Duration::from_millis(20), ..Default::default() };
+        let status = |slot: u64, level: &str| serde_json::json!([{ "slot": slot, "confirmations": 1, "err": null, "confirmationStatus": level }]);
+
+        // polling: processed isn't enough, confirmed is
+        let server = MockServer::start().await;
+        server
+            .on("sendTransaction", MockResponse::Result(serde_json::json!("sig1")))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(1, serde_json::json!([null]))))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(2, status(5, "processed"))))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(3, status(5, "confirmed"))))
+            .on("getBlockHeight", MockResponse::Result(serde_json::json!(50)));
+
+        let sender = TransactionSender::new(RpcClient::new(server.http_url()).unwrap()).with_config(config.clone());
+        let confirmation = sender.send_and_confirm("AQID", 100).await.unwrap();
+        assert_eq!(confirmation.signature, "sig1");
+        assert_eq!(confirmation.status, TransactionStatus::Confirmed { commitment: CommitmentLevel::Confirmed, slot: 5 });
+
+        let sends: Vec<serde_json::Value> = server.requests().into_iter().filter(|req| req["method"] == "sendTransaction").collect();
+        assert_eq!(sends[0]["params"], serde_json::json!(["AQID", {
+            "encoding": "base64", "skipPreflight": false, "preflightCommitment": "confirmed", "maxRetries": 0
+        }]));
+        assert!(sends.len() >= 2 && sends[1..].iter().all(|req| req["params"][1]["skipPreflight"] == true));
+
+        // block height passed lastValidBlockHeight
+        let server = MockServer::start().await;
+        server
+            .on("sendTransaction", MockResponse::Result(serde_json::json!("sig2")))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(1, serde_json::json!([null]))))
+            .on("getBlockHeight", MockResponse::Result(serde_json::json!(101)));
+        let sender = TransactionSender::new(RpcClient::new(server.http_url()).unwrap()).with_config(config.clone());
+        assert_eq!(sender.send_and_confirm("AQID", 100).await.unwrap().status, TransactionStatus::Expired);
+
+        // landed at processed, then the block height passed lastValidBlockHeight => still waits for confirmed
+        let server = MockServer::start().await;
+        server
+            .on("sendTransaction", MockResponse::Result(serde_json::json!("sig4")))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(1, status(5, "processed"))))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(2, status(5, "processed"))))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(3, status(5, "confirmed"))))
+            .on("getBlockHeight", MockResponse::Result(serde_json::json!(101)));
+        let sender = TransactionSender::new(RpcClient::new(server.http_url()).unwrap()).with_config(config.clone());
+        assert_eq!(
+            sender.send_and_confirm("AQID", 100).await.unwrap().status,
+            TransactionStatus::Confirmed { commitment: CommitmentLevel::Confirmed, slot: 5 }
+        );
+        assert_eq!(server.requests_of("getBlockHeight"), 0);
+        // landed at the first poll (t = 20ms), so at most the rebroadcast at t = 15ms went out
+        assert!(server.requests_of("sendTransaction") <= 2);
+
+        // a failure is reported at the target commitment as well
+        let failed = |level: &str| serde_json::json!([{ "slot": 6, "confirmations": 1, "err": { "InstructionError": [0, { "Custom": 1 }] }, "confirmationStatus": level }]);
+        let server = MockServer::start().await;
+        server
+            .on("sendTransaction", MockResponse::Result(serde_json::json!("sig5")))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(1, failed("processed"))))
+            .on("getSignatureStatuses", MockResponse::Result(with_context(2, failed("confirmed"))));
+        let sender = TransactionSender::new(RpcClient::new(server.http_url()).unwrap()).with_config(config.clone());
+        assert!(matches!(sender.send_and_confirm("AQID", 100).await.unwrap().status, TransactionStatus::Failed { slot: 6, .. }));
+        assert_eq!(server.requests_of("getSignatureStatuses"), 2);
+
+        // signatureSubscribe wins over (slow) polling
+        let server = MockServer::start().await;
+        server
+            .on("sendTransaction", MockResponse::Result(serde_json::json!("sig3")))
+            .push_ws_session(vec![
+                WsStep::Confirm(3),
+                WsStep::Notify(notification("signatureNotification", 3, with_context(9, serde_json::json!({ "err": { "InstructionError": [0, { "Custom": 6001 }] } })))),
+            ]);
+        let config = SenderConfig { poll_interval: Duration::from_secs(60), rebroadcast_interval: Duration::from_secs(60), ..config };
+        let sender = TransactionSender::new(RpcClient::new(server.http_url()).unwrap())
+            .with_pubsub(PubsubClient::connect(&server.ws_url()).await.unwrap())
+            .with_config(config);
+        let confirmation = sender.send_and_confirm("AQID", 100).await.unwrap();
+        assert!(matches!(confirmation.status, TransactionStatus::Failed { slot: 9, .. }));
+        assert_eq!(server.requests_of("getSignatureStatuses"), 0);
+        let subscribe: serde_json::Value = server.requests().into_iter().find(|req| req["method"] == "signatureSubscribe").unwrap();
+        assert_eq!(subscribe["params"], serde_json::json!(["sig3", { "commitment": "confirmed", "enableReceivedNotification": false }]));
+    }
+
+    #[tokio::test]
+    async fn account_subscribe_streams_until_close() {
+        use rpc::{
+            communication::{self, CommitmentLevel},
+            config::{AccountInfoConfig, DataSlice},
+            mock::{MockServer, WsStep, notification, account_value, with_context},
+        };
+
+        let server = MockServer::start().await;
+        server.push_ws_session(vec![
+            WsStep::Confirm(7),
+            WsStep::Ping,
+            WsStep::Notify(notification("accountNotification", 7, with_context(100, account_value(1, "AAAA")))),
+            WsStep::Notify(notification("accountNotification", 7, with_context(101, account_value(2, "AAAA")))),
+            WsStep::Close,
+        ]);
+
+        let config = AccountInfoConfig {
+            commitment: Some(CommitmentLevel::Confirmed),
+            data_slice: Some(DataSlice { offset: 8, length: 32 }),
+            min_context_slot: Some(90),
+            ..Default::default()
+        };
+        let subscription = communication::account_subscribe(server.ws_url(), "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", config).await.unwrap();
+        let notifications: Vec<_> = subscription.collect().await;
+
+        assert_eq!(notifications.len(), 2);
+        let last = notifications[1].as_ref().unwrap();
+        assert_eq!((last.slot, last.account.lamports), (101, 2));
+        assert_eq!(server.requests_of("accountSubscribe"), 1);
+        assert_eq!(server.requests()[0]["params"][1], serde_json::json!({
+            "commitment": "confirmed", "encoding": "base64", "dataSlice": { "offset": 8, "length": 32 }, "minContextSlot": 90
+        }));
+    }
+
+    #[tokio::test]
+    async fn supervised_subscription_reconnects_and_resubscribes() {
+        use std::time::Duration;
+        use rpc::{
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            keepalive::KeepaliveConfig,
+            mock::{MockServer, WsStep, notification, account_value, with_context},
+            subscription::{self, ReconnectPolicy, ConnectionEvent},
+        };
+
+        let server = MockServer::start().await;
+        server
+            .push_ws_session(vec![
+                WsStep::Confirm(1),
+                WsStep::Notify(notification("accountNotification", 1, with_context(100, account_value(1, "AAAA")))),
+                WsStep::Disconnect,
+            ])
+            .push_ws_session(vec![
+                WsStep::Confirm(2),
+                WsStep::Notify(notification("accountNotification", 2, with_context(105, account_value(2, "AAAA")))),
+            ]);
+
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let policy = ReconnectPolicy { initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_millis(50), max_attempts: Some(3) };
+        let mut subscription = subscription::account_subscribe_supervised(
+            &server.ws_url(), "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }, policy, KeepaliveConfig::default(), events_tx
+        );
+
+        assert_eq!(subscription.next().await.unwrap().unwrap().slot, 100);
+        assert_eq!(subscription.next().await.unwrap().unwrap().slot, 105);
+
+        let mut seen = Vec::new();
+        while let Ok(event) = events.try_recv() { seen.push(event); }
+        assert_eq!(seen, vec![
+            ConnectionEvent::Connected,
+            ConnectionEvent::Reconnecting(1),
+            ConnectionEvent::Connected,
+            ConnectionEvent::Resubscribed,
+        ]);
+        assert_eq!(server.requests_of("accountSubscribe"), 2);
+    }
+
+    #[tokio::test]
+    async fn supervised_subscription_resyncs_after_reconnect() {
+        use std::time::Duration;
+        use rpc::{
+            client::RpcClient,
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            keepalive::KeepaliveConfig,
+            mock::{MockServer, MockResponse, WsStep, notification, account_value, with_context},
+            subscription::{self, ReconnectPolicy, ConnectionEvent, SubscriptionTarget},
+        };
+
+        let notify = |id: u64, slot: u64, data: &str| WsStep::Notify(notification("accountNotification", id, with_context(slot, account_value(1, data))));
+        let server = MockServer::start().await;
+        server
+            .push_ws_session(vec![WsStep::Confirm(1), notify(1, 100, "AAAA"), WsStep::Disconnect])
+            // 99 is older than the resynced state, so it's dropped
+            .push_ws_session(vec![WsStep::Confirm(2), notify(2, 99, "BBBB"), notify(2, 110, "CCCC"), WsStep::Disconnect])
+            .push_ws_session(vec![WsStep::Confirm(3), notify(3, 120, "DDDD")])
+            .on("getAccountInfo", MockResponse::Result(with_context(105, account_value(1, "BBBB"))))
+            // nothing changed while the third session was being set up => no resync
+            .on("getAccountInfo", MockResponse::Result(with_context(112, account_value(1, "CCCC"))));
+
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let policy = ReconnectPolicy { initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_millis(50), max_attempts: Some(3) };
+        let target = SubscriptionTarget::new(&server.ws_url()).with_resync(RpcClient::new(server.http_url()).unwrap());
+        let mut subscription = subscription::account_subscribe_supervised(
+            target, "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }, policy, KeepaliveConfig::default(), events_tx
+        );
+
+        // the fetched state is told apart in the stream itself
+        let mut slots = Vec::new();
+        for _ in 0..4 {
+            let notification = subscription.next().await.unwrap().unwrap();
+            slots.push((notification.slot, notification.resync));
+        }
+        assert_eq!(slots, vec![(100, false), (105, true), (110, false), (120, false)]);
+
+        let mut seen = Vec::new();
+        while let Ok(event) = events.try_recv() { seen.push(event); }
+        assert_eq!(seen, vec![
+            ConnectionEvent::Connected,
+            ConnectionEvent::Reconnecting(1),
+            ConnectionEvent::Connected,
+            ConnectionEvent::Resubscribed,
+            ConnectionEvent::Resynced(105),
+            ConnectionEvent::Reconnecting(1),
+            ConnectionEvent::Connected,
+            ConnectionEvent::Resubscribed,
+        ]);
+
+        let fetches: Vec<serde_json::Value> = server.requests().into_iter().filter(|r| r["method"] == "getAccountInfo").collect();
+        assert_eq!(fetches.len(), 2);
+        assert_eq!(fetches[0]["params"][1]["minContextSlot"], 100);
+        assert_eq!(fetches[0]["params"][1]["encoding"], "base64");
+        assert_eq!(fetches[1]["params"][1]["minContextSlot"], 110);
+    }
+
+    #[tokio::test]
+    async fn cassette_replays_recorded_traffic_without_network() {
+        use std::time::{Duration, Instant};
+        use rpc::{
+            cassette::{Cassette, CassetteEntry, CassetteEvent},
+            client::RpcClient,
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            error::RpcError,
+            keepalive::KeepaliveConfig,
+            mock::{MockServer, MockResponse, WsStep, notification, account_value, with_context},
+            subscription::{self, ReconnectPolicy, ConnectionEvent, SubscriptionTarget},
+        };
+
+        let path = std::env::temp_dir().join(format!("core_concepts-cassette-{}.jsonl", std::process::id()));
+        let notify = |id: u64, slot: u64| WsStep::Notify(notification("accountNotification", id, with_context(slot, account_value(1, "AAAA"))));
+        let server = MockServer::start().await;
+        server
+            .on("getSlot", MockResponse::Result(serde_json::json!(42)))
+            .push_ws_session(vec![WsStep::Confirm(1), notify(1, 100), WsStep::Sleep(Duration::from_millis(150)), notify(1, 101), WsStep::Disconnect])
+            .push_ws_session(vec![WsStep::Confirm(2), notify(2, 105)]);
+
+        // everything goes through the same code paths, once with the network and once from the cassette
+        let run = |cassette: Cassette| {
+            let http_url = server.http_url();
+            let ws_url = server.ws_url();
+            async move {
+                let client = RpcClient::new(http_url).unwrap().with_cassette(cassette.clone());
+                let slot = client.get_slot().await.unwrap();
+
+                let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+                let policy = ReconnectPolicy { initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_millis(50), max_attempts: Some(3) };
+                let mut subscription = subscription::account_subscribe_supervised(
+                    SubscriptionTarget::new(&ws_url).with_cassette(cassette),
+                    "3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }, policy, KeepaliveConfig::default(), events_tx
+                );
+                let mut slots = vec![subscription.next().await.unwrap().unwrap().slot];
+                let first_at = Instant::now();
+                slots.push(subscription.next().await.unwrap().unwrap().slot);
+                let gap = first_at.elapsed();
+                slots.push(subscription.next().await.unwrap().unwrap().slot);
+
+                let mut seen = Vec::new();
+                while let Ok(event) = events.try_recv() { seen.push(event); }
+                (client, slot, slots, seen, gap)
+            }
+        };
+
+        let (_, slot, slots, events, gap) = run(Cassette::record(&path).unwrap()).await;
+        assert!(gap >= Duration::from_millis(100));
+        let requests = server.requests().len();
+
+        let entries: Vec<CassetteEntry> = std::fs::read_to_string(&path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert!(matches!(&entries[0].event, CassetteEvent::Http { status: 200, .. }));
+        assert_eq!(entries.iter().filter(|e| matches!(e.event, CassetteEvent::WsConnect { .. })).count(), 2);
+        assert!(entries.iter().any(|e| matches!(e.event, CassetteEvent::WsError { conn: 0, .. } | CassetteEvent::WsEnd { conn: 0 })));
+
+        let (client, replayed_slot, replayed_slots, replayed_events, replayed_gap) = run(Cassette::replay(&path).unwrap()).await;
+        // frames keep their offsets from the connect
+        assert!(replayed_gap.abs_diff(gap) < Duration::from_millis(50), "{replayed_gap:?} vs {gap:?}");
+        assert_eq!((replayed_slot, replayed_slots.clone()), (slot, slots));
+        assert_eq!(replayed_slots, vec![100, 101, 105]);
+        assert_eq!(replayed_events, events);
+        assert_eq!(replayed_events[..3], [ConnectionEvent::Connected, ConnectionEvent::Reconnecting(1), ConnectionEvent::Connected]);
+        // the mock saw nothing new
+        assert_eq!(server.requests().len(), requests);
+
+        // repeated request => last recorded response, unknown one => error
+        assert_eq!(client.get_slot().await.unwrap(), 42);
+        assert!(matches!(client.get_block_height().await, Err(RpcError::Cassette(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pubsub_client_routes_and_unsubscribes() {
+        use rpc::{
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            mock::{MockServer, WsStep, notification, account_value, with_context},
+            pubsub::PubsubClient,
+        };
+
+        let server = MockServer::start().await;
+        server.push_ws_session(vec![
+            WsStep::Confirm(10),
+            WsStep::Confirm(20),
+            // interleaved notifications of both subscriptions
+            WsStep::Notify(notification("accountNotification", 20, with_context(5, account_value(200, "AAAA")))),
+            WsStep::Notify(notification("accountNotification", 10, with_context(6, account_value(100, "AAAA")))),
+            WsStep::Notify(notification("slotNotification", 99, serde_json::json!({ "parent": 1, "root": 0, "slot": 2 }))),
+            WsStep::Respond(serde_json::json!(true)),
+        ]);
+
+        let client = PubsubClient::connect(&server.ws_url()).await.unwrap();
+        let mut a = client.account_subscribe("A", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }).await.unwrap();
+        let mut b = client.account_subscribe("B", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }).await.unwrap();
+
+        assert_eq!(a.next().await.unwrap().unwrap().account.lamports, 100);
+        assert_eq!(b.next().await.unwrap().unwrap().account.lamports, 200);
+
+        drop(b);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2]["method"], "accountUnsubscribe");
+        assert_eq!(requests[2]["params"], serde_json::json!([20]));
+        assert_eq!(server.ws_connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn pubsub_closes_a_stalled_subscriber() {
+        use rpc::{
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            error::RpcError,
+            mock::{MockServer, WsStep, notification, account_value, with_context},
+            pubsub::PubsubClient,
+        };
+
+        const BUFFER: u64 = 1024;  // subscription::SUBSCRIPTION_BUFFER
+        let mut script: Vec<WsStep> = vec![WsStep::Confirm(1), WsStep::Confirm(2)];
+        script.extend((0..BUFFER + 100).map(|slot| WsStep::Notify(notification("accountNotification", 1, with_context(slot, account_value(1, "AAAA"))))));
+        script.push(WsStep::Notify(notification("accountNotification", 2, with_context(7, account_value(2, "AAAA")))));
+        script.push(WsStep::Respond(serde_json::json!(true)));
+        let server = MockServer::start().await;
+        server.push_ws_session(script);
+
+        let client = PubsubClient::connect(&server.ws_url()).await.unwrap();
+        let config = AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() };
+        let mut stalled = client.account_subscribe("A", config.clone()).await.unwrap();
+        let mut other = client.account_subscribe("B", config).await.unwrap();
+
+        // nobody reads `stalled`, the other subscription is still served
+        assert_eq!(other.next().await.unwrap().unwrap().slot, 7);
+
+        // the buffered notifications, then the explicit lag error, then the end of the stream
+        let mut slots: Vec<u64> = Vec::new();
+        let e: RpcError = loop {
+            match stalled.next().await.unwrap() {
+                Ok(notification) => slots.push(notification.slot),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(slots, (0..BUFFER).collect::<Vec<_>>());
+        assert!(matches!(e, RpcError::Lagged(1024)) && e.is_retryable());
+        assert!(stalled.next().await.is_none());
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let requests = server.requests();
+        assert_eq!(requests.last().unwrap()["method"], "accountUnsubscribe");
+        assert_eq!(requests.last().unwrap()["params"], serde_json::json!([1]));
+    }
+
+    #[tokio::test]
+    async fn pubsub_parses_every_notification_kind_and_unsubscribes() {
+        use rpc::{
+            communication::CommitmentLevel,
+            config::{AccountInfoConfig, LogsSubscribeConfig},
+            encoding::UiEncoding,
+            mock::{MockServer, WsStep, notification, account_value, with_context},
+            pubsub::{BlockFilter, LogsFilter, ProgramFilter, PubsubClient, TransactionDetails},
+            types::SlotInfo,
+        };
+
+        let block = serde_json::json!({
+            "slot": 42, "err": null,
+            "block": {
+                "blockhash": "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N", "previousBlockhash": "11111111111111111111111111111111",
+                "parentSlot": 41, "blockTime": 1742000000, "blockHeight": 30, "signatures": ["sig1", "sig2"]
+            }
+        });
+        let server = MockServer::start().await;
+        server.push_ws_session(vec![
+            WsStep::Confirm(1),
+            WsStep::Confirm(2),
+            WsStep::Confirm(3),
+            WsStep::Confirm(4),
+            WsStep::Confirm(5),
+            WsStep::Notify(notification("logsNotification", 1, with_context(40, serde_json::json!({
+                "signature": "sig1", "err": null, "logs": ["Program 11111111111111111111111111111111 invoke [1]"]
+            })))),
+            WsStep::Notify(notification("programNotification", 2, with_context(41, serde_json::json!({
+                "pubkey": "PDA", "account": account_value(7, "AAAA")
+            })))),
+            WsStep::Notify(notification("slotNotification", 3, serde_json::json!({ "parent": 41, "root": 10, "slot": 42 }))),
+            WsStep::Notify(notification("rootNotification", 4, serde_json::json!(11))),
+            WsStep::Notify(notification("blockNotification", 5, with_context(42, block))),
+            WsStep::Respond(serde_json::json!(true)),
+            WsStep::Respond(serde_json::json!(true)),
+            WsStep::Respond(serde_json::json!(true)),
+            WsStep::Respond(serde_json::json!(true)),
+            WsStep::Respond(serde_json::json!(true)),
+        ]);
+
+        let client = PubsubClient::connect(&server.ws_url()).await.unwrap();
+        let mut logs = client.logs_subscribe(LogsFilter::Mentions(vec!["PDA".into()]), LogsSubscribeConfig { commitment: Some(CommitmentLevel::Confirmed) }).await.unwrap();
+        let config = AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() };
+        let mut program = client.program_subscribe("Prog", vec![ProgramFilter::DataSize(16)], config).await.unwrap();
+        let mut slots = client.slot_subscribe().await.unwrap();
+        let mut roots = client.root_subscribe().await.unwrap();
+        let mut blocks = client.block_subscribe(BlockFilter::All, TransactionDetails::Signatures, CommitmentLevel::Confirmed, UiEncoding::Base64).await.unwrap();
+
+        let log = logs.next().await.unwrap().unwrap();
+        assert_eq!((log.slot, log.signature.as_str(), log.logs.len()), (40, "sig1", 1));
+        let account = program.next().await.unwrap().unwrap();
+        assert_eq!((account.slot, account.pubkey.as_str(), account.account.lamports), (41, "PDA", 7));
+        assert_eq!(slots.next().await.unwrap().unwrap(), SlotInfo { parent: 41, root: 10, slot: 42 });
+        assert_eq!(roots.next().await.unwrap().unwrap(), 11);
+        let block = blocks.next().await.unwrap().unwrap();
+        assert_eq!((block.slot, block.err), (42, None));
+        assert_eq!(block.block.unwrap().signatures.unwrap(), vec!["sig1", "sig2"]);
+
+        // one by one, so the unsubscribe frames come in a known order
+        let pause = || tokio::time::sleep(std::time::Duration::from_millis(50));
+        drop(logs);
+        pause().await;
+        drop(program);
+        pause().await;
+        drop(slots);
+        pause().await;
+        drop(roots);
+        pause().await;
+        drop(blocks);
+        pause().await;
+
+        let requests = server.requests();
+        let subscribes: Vec<&str> = requests[..5].iter().map(|req| req["method"].as_str().unwrap()).collect();
+        assert_eq!(subscribes, vec!["logsSubscribe", "programSubscribe", "slotSubscribe", "rootSubscribe", "blockSubscribe"]);
+        assert_eq!(requests[0]["params"][1], serde_json::json!({ "commitment": "confirmed" }));
+        assert_eq!(requests[1]["params"][1]["filters"], serde_json::json!([{ "dataSize": 16 }]));
+        let unsubscribes: Vec<(&str, serde_json::Value)> = requests[5..].iter()
+            .map(|req| (req["method"].as_str().unwrap(), req["params"].clone()))
+            .collect();
+        assert_eq!(unsubscribes, vec![
+            ("logsUnsubscribe", serde_json::json!([1])),
+            ("programUnsubscribe", serde_json::json!([2])),
+            ("slotUnsubscribe", serde_json::json!([3])),
+            ("rootUnsubscribe", serde_json::json!([4])),
+            ("blockUnsubscribe", serde_json::json!([5])),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn anchor_accounts_decode_from_subscription() {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use programs::smart_contracts::PDAmeta;
+        use rpc::{
+            anchor::{AccountDeserialize, account_discriminator, decode_accounts},
+            communication::CommitmentLevel,
+            config::AccountInfoConfig,
+            error::RpcError,
+            mock::{MockServer, WsStep, notification, account_value, with_context},
+            pubsub::PubsubClient,
+        };
+
+        assert_eq!(PDAmeta::DISCRIMINATOR, account_discriminator("PDAmeta"));
+
+        let pda_meta = |discriminator: [u8; 8], counter: u64| -> String {
+            let mut data: Vec<u8> = discriminator.to_vec();
+            data.extend(counter.to_le_bytes());
+            data.extend([254, 0, 0, 0, 0, 0, 0, 0]);
+            assert_eq!(data.len(), PDAmeta::LEN);
+            BASE64.encode(data)
+        };
+
+        let server = MockServer::start().await;
+        server.push_ws_session(vec![
+            WsStep::Confirm(1),
+            WsStep::Notify(notification("accountNotification", 1, with_context(10, account_value(1, &pda_meta(PDAmeta::DISCRIMINATOR, 1))))),
+            WsStep::Notify(notification("accountNotification", 1, with_context(11, account_value(1, &pda_meta([0; 8], 2))))),
+            WsStep::Notify(notification("accountNotification", 1, with_context(12, account_value(1, &pda_meta(PDAmeta::DISCRIMINATOR, 3))))),
+        ]);
+
+        let client = PubsubClient::connect(&server.ws_url()).await.unwrap();
+        let subscription = client.account_subscribe("PDA", AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }).await.unwrap();
+        let mut metas = decode_accounts::<PDAmeta, _>(subscription);
+
+        let first = metas.next().await.unwrap().unwrap();
+        assert_eq!((first.slot, first.account), (10, PDAmeta { counter: 1, bump_seed: 254 }));
+        // foreign account type => error item, the stream goes on
+        assert!(matches!(metas.next().await.unwrap(), Err(RpcError::Decode(_))));
+        assert_eq!(metas.next().await.unwrap().unwrap().account.counter, 3);
+    }
+
+    #[tokio::test]
+    async fn smart_contracts_client_derives_pda_and_builds_instructions() {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use programs::{
+            pubkey::{Pubkey, SYSTEM_PROGRAM_ID},
+            smart_contracts::{self, PDAmeta, PROGRAM_ID},
+        };
+        use rpc::{
+            anchor::{AccountDeserialize, instruction_discriminator},
+            client::RpcClient,
+            mock::{MockServer, MockResponse, account_value, with_context},
+        };
+
+        assert_eq!(PROGRAM_ID.to_string(), "wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5");
+        assert_eq!(SYSTEM_PROGRAM_ID.to_string(), "11111111111111111111111111111111");
+        assert_eq!(smart_contracts::INIT_PDA_DISCRIMINATOR, instruction_discriminator("init_pda"));
+        assert_eq!(smart_contracts::UPDATE_PDA_DISCRIMINATOR, instruction_discriminator("update_pda"));
+
+        let signer: Pubkey = "4wBqpZM9xaSheZzJSMawUKKwhdpChKbZ5eu5ky4Vigw".parse().unwrap();
+        let (meta, bump) = smart_contracts::find_meta_address(&signer);
+        assert_eq!((meta.to_string().as_str(), bump), ("9v93saSdKnNTffKsAZPwQ3eRTuxccNHVUy3VydYAaQy9", 255));
+        assert!(!meta.is_on_curve());
+        assert_eq!(Pubkey::create_program_address(&[b"meta", signer.as_bytes(), &[bump]], &PROGRAM_ID).unwrap(), Some(meta));
+        assert!(Pubkey::new(curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED.to_bytes()).is_on_curve());
+        assert!("abc".parse::<Pubkey>().is_err());
+
+        let init = smart_contracts::init_pda(&signer);
+        assert_eq!(init.program_id, PROGRAM_ID);
+        assert_eq!(init.data, smart_contracts::INIT_PDA_DISCRIMINATOR);
+        let metas: Vec<(Pubkey, bool, bool)> = init.accounts.iter().map(|a| (a.pubkey, a.is_signer, a.is_writable)).collect();
+        assert_eq!(metas, vec![(meta, false, true), (signer, true, true), (SYSTEM_PROGRAM_ID, false, false)]);
+
+        let update = smart_contracts::update_pda(&signer);
+        assert_eq!(update.data, smart_contracts::UPDATE_PDA_DISCRIMINATOR);
+        let metas: Vec<(Pubkey, bool, bool)> = update.accounts.iter().map(|a| (a.pubkey, a.is_signer, a.is_writable)).collect();
+        assert_eq!(metas, vec![(meta, false, true), (signer, true, false), (SYSTEM_PROGRAM_ID, false, false)]);
+
+        let mut data: Vec<u8> = PDAmeta::DISCRIMINATOR.to_vec();
+        data.extend(7u64.to_le_bytes());
+        data.extend([bump, 0, 0, 0, 0, 0, 0, 0]);
+
+        let server = MockServer::start().await;
+        server
+            .on("getAccountInfo", MockResponse::Result(with_context(5, account_value(1, &BASE64.encode(data)))))
+            .on("getAccountInfo", MockResponse::Result(with_context(6, serde_json::Value::Null)));
+        let client = RpcClient::new(server.http_url()).unwrap();
+        let fetched = smart_contracts::fetch_meta(&client, &signer).await.unwrap();
+        assert_eq!(fetched, Some(PDAmeta { counter: 7, bump_seed: 255 }));
+        assert_eq!(server.requests()[0]["params"][0], meta.to_string());
+
+        // not initialized yet
+        assert_eq!(smart_contracts::fetch_meta(&client, &signer).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn priority_fee_estimator_prepends_compute_budget() {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use programs::{
+            compute_budget::{self, COMPUTE_BUDGET_PROGRAM_ID, MAX_COMPUTE_UNIT_LIMIT},
+            instruction::{AccountMeta, Instruction},
+            message::Message,
+            priority_fee::{self, ComputeBudget, PriorityFeeConfig, PriorityFeeEstimator},
+            pubkey::{Pubkey, SYSTEM_PROGRAM_ID},
+            smart_contracts::{self, PROGRAM_ID},
+        };
+        use rpc::{client::RpcClient, encoding::decode_transaction, error::RpcError, mock::{MockServer, MockResponse, with_context}};
+
+        assert_eq!(COMPUTE_BUDGET_PROGRAM_ID.to_string(), "ComputeBudget111111111111111111111111111111");
+        assert_eq!(priority_fee::percentile(&[], 75), 0);
+        assert_eq!(priority_fee::percentile(&[5, 1, 3, 2, 4], 50), 3);
+        assert_eq!(priority_fee::percentile(&[5, 1, 3, 2, 4], 100), 5);
+        assert_eq!(priority_fee::percentile(&[5, 1, 3, 2, 4], 0), 1);
+
+        // the serialized message is what the wire parser reads back
+        let signer: Pubkey = "4wBqpZM9xaSheZzJSMawUKKwhdpChKbZ5eu5ky4Vigw".parse().unwrap();
+        let (meta, _) = smart_contracts::find_meta_address(&signer);
+        let budget = ComputeBudget { unit_limit: 3_300, unit_price: 80, units_consumed: 3_000 };
+        let instructions = budget.prepend_to(vec![compute_budget::set_compute_unit_price(1), smart_contracts::update_pda(&signer)]);
+        assert_eq!(instructions.len(), 3);
+
+        let message = Message::new(&instructions, &signer, [7; 32]).unwrap();
+        assert_eq!(message.writable_accounts().copied().collect::<Vec<_>>(), vec![signer, meta]);
+        // the header counts and the indices are u8
+        let keys: Vec<Pubkey> = (0..=255u8).map(|i| Pubkey::new([i; 32])).collect();
+        let signers = Instruction { program_id: PROGRAM_ID, accounts: keys.iter().map(|&key| AccountMeta::readonly(key, true)).collect(), data: Vec::new() };
+        assert!(matches!(Message::new(&[signers], &signer, [7; 32]), Err(RpcError::InvalidRequest(_))));
+        let accounts = Instruction { program_id: PROGRAM_ID, accounts: keys.iter().map(|&key| AccountMeta::readonly(key, false)).collect(), data: Vec::new() };
+        assert!(matches!(Message::new(&[accounts], &signer, [7; 32]), Err(RpcError::InvalidRequest(_))));
+        let tx = decode_transaction(&BASE64.decode(message.to_unsigned_transaction_base64()).unwrap()).unwrap();
+        assert_eq!(tx.signatures, vec!["1111111111111111111111111111111111111111111111111111111111111111".to_string()]);
+        assert_eq!(
+            (tx.message.header.num_required_signatures, tx.message.header.num_readonly_signed_accounts, tx.message.header.num_readonly_unsigned_accounts),
+            (1, 0, 3)
+        );
+        let keys: Vec<String> = [signer, meta, COMPUTE_BUDGET_PROGRAM_ID, SYSTEM_PROGRAM_ID, PROGRAM_ID].iter().map(Pubkey::to_string).collect();
+        assert_eq!(tx.message.account_keys, keys);
+        assert_eq!(tx.message.recent_blockhash, bs58::encode([7; 32]).into_string());
+        assert_eq!(tx.message.instructions[0].data, bs58::encode([2, 0xe4, 0x0c, 0, 0]).into_string());
+        assert_eq!(tx.message.instructions[1].data, bs58::encode([3, 80, 0, 0, 0, 0, 0, 0, 0]).into_string());
+        assert_eq!((tx.message.instructions[2].program_id_index, tx.message.instructions[2].accounts.clone()), (4, vec![1, 0, 3]));
+
+        let fees: Vec<serde_json::Value> = (0..11u64).map(|i| serde_json::json!({ "slot": 100 + i, "prioritizationFee": i * 10 })).collect();
+        let server = MockServer::start().await;
+        server
+            .on("getRecentPrioritizationFees", MockResponse::Result(serde_json::json!(fees)))
+            .on("simulateTransaction", MockResponse::Result(with_context(9, serde_json::json!({ "err": null, "logs": [], "unitsConsumed": 3_000 }))))
+            .on("simulateTransaction", MockResponse::Result(with_context(9, serde_json::json!({
+                "err": { "InstructionError": [2, { "Custom": 2006 }] },
+                "logs": ["Program log: AnchorError"],
+                "unitsConsumed": 1_200
+            }))));
+        let client = RpcClient::new(server.http_url()).unwrap();
+        let estimator = PriorityFeeEstimator::new(client).with_config(PriorityFeeConfig { percentile: 75, ..Default::default() });
+
+        let estimate = estimator.estimate(&[smart_contracts::update_pda(&signer)], &signer).await.unwrap();
+        assert_eq!(estimate, ComputeBudget { unit_limit: 3_300, unit_price: 80, units_consumed: 3_000 });
+        assert_eq!(estimate.priority_fee_lamports(), 1);
+        let absurd = ComputeBudget { unit_limit: u32::MAX, unit_price: u64::MAX, units_consumed: 0 };
+        assert_eq!(absurd.priority_fee_lamports(), u64::MAX);
+        let capped = ComputeBudget { unit_limit: u32::MAX, unit_price: 1_000_000, units_consumed: 0 };
+        assert_eq!(capped.priority_fee_lamports(), u64::from(MAX_COMPUTE_UNIT_LIMIT));
+
+        let requests = server.requests();
+        let fees_request = requests.iter().find(|r| r["method"] == "getRecentPrioritizationFees").unwrap();
+        assert_eq!(fees_request["params"], serde_json::json!([[signer.to_string(), meta.to_string()]]));
+        let simulate_request = requests.iter().find(|r| r["method"] == "simulateTransaction").unwrap();
+        assert_eq!(simulate_request["params"][1]["encoding"], "base64");
+        assert_eq!(simulate_request["params"][1]["sigVerify"], false);
+        assert_eq!(simulate_request["params"][1]["replaceRecentBlockhash"], true);
+        let simulated = decode_transaction(&BASE64.decode(simulate_request["params"][0].as_str().unwrap()).unwrap()).unwrap();
+        let mut max_limit: Vec<u8> = vec![2];
+        max_limit.extend(MAX_COMPUTE_UNIT_LIMIT.to_le_bytes());
+        assert_eq!(simulated.message.instructions[0].data, bs58::encode(max_limit).into_string());
+
+        match estimator.with_compute_budget(vec![smart_contracts::update_pda(&signer)], &signer).await {
+            Err(RpcError::SimulationFailed { err, logs }) => {
+                assert_eq!(err["InstructionError"][1]["Custom"], 2006);
+                assert_eq!(logs.len(), 1);
+            },
+            other => panic!("expected SimulationFailed, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn merged_subscriptions_dedup_and_stay_slot_monotonic() {
+        use std::time::Duration;
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use tokio::sync::mpsc;
+        use rpc::{
+            error::RpcError,
+            merge::{Merged, merge_notifications},
+            mock::{account_value, with_context},
+            subscription::Subscription,
+            types::AccountNotification,
+        };
+
+        let update = |slot: u64, data: &[u8], encoding: &str| -> Result<AccountNotification, RpcError> {
+            let mut account = account_value(1, "");
+            account["data"] = match encoding {
+                "base64+zstd" => serde_json::json!([BASE64.encode(zstd::encode_all(data, 3).unwrap()), encoding]),
+                _ => serde_json::json!([BASE64.encode(data), encoding]),
+            };
+            Ok(serde_json::from_value(with_context(slot, account)).unwrap())
+        };
+
+        let (a_tx, a_rx) = mpsc::channel(16);
+        let (b_tx, b_rx) = mpsc::channel(16);
+        let mut merged = merge_notifications(vec![Subscription::new(a_rx), Subscription::new(b_rx)]);
+
+        a_tx.send(update(10, b"one", "base64")).await.unwrap();
+        let Merged { source, notification } = merged.next().await.unwrap().unwrap();
+        assert_eq!((source, notification.slot), (0, 10));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // same state through another encoding => duplicate, then B is first with slot 11
+        b_tx.send(update(10, b"one", "base64+zstd")).await.unwrap();
+        b_tx.send(update(11, b"two", "base64")).await.unwrap();
+        let Merged { source, notification } = merged.next().await.unwrap().unwrap();
+        assert_eq!((source, notification.slot), (1, 11));
+
+        // A catches up: its copy of 11 is late, its older (never seen) 10' is stale, new content at 11 passes
+        a_tx.send(update(11, b"two", "base64")).await.unwrap();
+        a_tx.send(update(10, b"one'", "base64")).await.unwrap();
+        a_tx.send(update(11, b"three", "base64")).await.unwrap();
+        let Merged { source, notification } = merged.next().await.unwrap().unwrap();
+        assert_eq!((source, notification.slot), (0, 11));
+        assert_eq!(notification.account.data.decode().unwrap(), b"three");
+
+        // errors pass through, the other source goes on
+        a_tx.send(Err(RpcError::WsClosed(None))).await.unwrap();
+        assert!(matches!(merged.next().await.unwrap(), Err(RpcError::WsClosed(None))));
+        drop(a_tx);
+        b_tx.send(update(12, b"four", "base64")).await.unwrap();
+        assert_eq!(merged.next().await.unwrap().unwrap().source, 1);
+        drop(b_tx);
+        assert!(merged.next().await.is_none());
+
+        let stats = merged.stats();
+        assert_eq!((stats[0].first, stats[0].late, stats[0].stale), (2, 1, 1));
+        assert_eq!((stats[1].first, stats[1].late, stats[1].stale), (2, 1, 0));
+        assert!(stats[1].mean_lag().unwrap() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn response_cache_serves_finalized_transactions() {
+        use rpc::{
+            cache::{CacheConfig, CacheStats, ResponseCache},
+            client::RpcClient,
+            communication::CommitmentLevel,
+            config::{TransactionConfig, TxCommitment},
+            mock::{MockServer, MockResponse},
+        };
+
+        let tx: serde_json::Value = serde_json::json!({
+            "slot": 327147000, "blockTime": 1742000000, "version": 0, "meta": null,
+            "transaction": {"signatures": ["yn5n7ke3b59mVaUTJVKb6gA9C5xr2jVeqadKk1vUDMjAX6LrCYi365D8qrRsDLC3TwGpCPvnt24w37fqL3mLevP"],
+                "message": {"header": {"numRequiredSignatures": 1, "numReadonlySignedAccounts": 0, "numReadonlyUnsignedAccounts": 1},
+                    "accountKeys": ["3AbG3ZA19fJKjTSTMTCz7j2bodPagXog4PwTBi8H7UA4", "11111111111111111111111111111111"],
+                    "recentBlockhash": "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N",
+                    "instructions": [{"programIdIndex": 1, "accounts": [0], "data": "3Bxs4h24hBtQy9rw"}]}}
+        });
+        let dir = std::env::temp_dir().join(format!("core_concepts-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let server = MockServer::start().await;
+        server
+            .on("getTransaction", MockResponse::Result(serde_json::Value::Null))
+            .on("getTransaction", MockResponse::Result(tx));
+
+        let cache = ResponseCache::new(CacheConfig { capacity: 1, disk_dir: Some(dir.clone()) }).unwrap();
+        let client = RpcClient::new_with_commitment(server.http_url(), CommitmentLevel::Finalized).unwrap().with_cache(cache.clone());
+
+        // not found yet => not cached, the next call goes to the node
+        assert!(client.get_transaction("sig-a").await.unwrap().is_none());
+        assert_eq!(client.get_transaction("sig-a").await.unwrap().unwrap().slot, 327147000);
+        assert_eq!(client.get_transaction("sig-a").await.unwrap().unwrap().slot, 327147000);
+        assert_eq!(server.requests_of("getTransaction"), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, disk_hits: 0, misses: 2, entries: 1 });
+
+        // confirmed may still be rolled back
+        let confirmed = TransactionConfig { commitment: Some(TxCommitment::Confirmed), ..Default::default() };
+        client.get_transaction_with_config("sig-a", confirmed.clone()).await.unwrap();
+        client.get_transaction_with_config("sig-a", confirmed).await.unwrap();
+        assert_eq!(server.requests_of("getTransaction"), 4);
+
+        // capacity 1 => "sig-a" is evicted from memory, but still on disk
+        client.get_transaction("sig-b").await.unwrap();
+        assert_eq!(server.requests_of("getTransaction"), 5);
+        client.get_transaction("sig-a").await.unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 2, disk_hits: 1, misses: 3, entries: 1 });
+
+        // a fresh cache over the same directory (e.g. after a restart)
+        let restarted = ResponseCache::new(CacheConfig { capacity: 10, disk_dir: Some(dir.clone()) }).unwrap();
+        let client = client.with_cache(restarted.clone());
+        client.get_transaction("sig-b").await.unwrap();
+        assert_eq!(server.requests_of("getTransaction"), 5);
+        assert_eq!(restarted.stats(), CacheStats { hits: 1, disk_hits: 1, misses: 0, entries: 1 });
+
+        // every temp file was renamed into place
+        let names: Vec<String> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().all(|name| name.ends_with(".json")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn identical_concurrent_requests_are_coalesced() {
+        use std::task::Poll;
+        use rpc::{
+            client::RpcClient,
+            communication::CommitmentLevel,
+            config::ContextConfig,
+            error::RpcError,
+            mock::{MockServer, MockResponse, with_context},
+        };
+
+        const ALICE: &str = "4wBqpZM9xaSheZzJSMawUKKwhdpChKbZ5eu5ky4Vigw";
+        let server = MockServer::start().await;
+        server
+            .on("getBalance", MockResponse::Result(with_context(100, serde_json::json!(5))))
+            .on("getSlot", MockResponse::Error { code: -32602, message: "Invalid params".into() });
+        let client = RpcClient::new(server.http_url()).unwrap().with_coalescing();
+
+        // every call registers before the first answer arrives, clones share the flights
+        let clone: RpcClient = client.clone();
+        let (a, b, c) = tokio::join!(client.get_balance(ALICE), client.get_balance(ALICE), clone.get_balance(ALICE));
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (5, 5, 5));
+        assert_eq!(server.requests_of("getBalance"), 1);
+        assert_eq!(client.coalesced_requests(), 2);
+
+        // other commitment => other params, and a finished flight isn't reused
+        let finalized = ContextConfig { commitment: Some(CommitmentLevel::Finalized), ..Default::default() };
+        let (a, b) = tokio::join!(client.get_balance(ALICE), client.get_balance_with_config(ALICE, finalized));
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(server.requests_of("getBalance"), 3);
+
+        // the error is shared, its code is still visible
+        let (a, b) = tokio::join!(client.get_slot(), client.get_slot());
+        for e in [a.unwrap_err(), b.unwrap_err()] {
+            assert!(matches!(e, RpcError::Coalesced(_)));
+            assert_eq!(e.code(), Some(-32602));
+            assert!(!e.is_retryable());
+        }
+        assert_eq!(server.requests_of("getSlot"), 1);
+        // same shape without anyone to share with
+        assert!(matches!(client.get_slot().await, Err(RpcError::Coalesced(_))));
+
+        // the leader is dropped mid-flight => the waiting caller sends the request itself
+        let mut leader = Box::pin(client.get_balance(ALICE));
+        let mut follower = Box::pin(client.get_balance(ALICE));
+        assert!(matches!(futures_util::poll!(&mut leader), Poll::Pending));
+        assert!(matches!(futures_util::poll!(&mut follower), Poll::Pending));
+        drop(leader);
+        let res = tokio::time::timeout(std::time::Duration::from_secs(1), follower).await.expect("the follower kept waiting");
+        assert_eq!(res.unwrap(), 5);
+        // the dropped request may or may not have reached the node, the follower's one did
+        assert!(server.requests_of("getBalance") >= 4);
+        assert_eq!(client.coalesced_requests(), 3);
+    }
+
     const ITERS: usize = 1_000_000;
 
     #[bench]
@@ -0,0 +1,34 @@
+use super::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+};
+
+/// `ComputeBudget111111111111111111111111111111`
+#[allow(dead_code)]
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = Pubkey::new([
+    3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231,
+    188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+]);
+
+/// Max compute units of a transaction.
+#[allow(dead_code)]
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// ### Caps the compute units of the transaction
+/// Without it the limit is 200k per instruction, and the priority fee is charged for the limit, not for the units used.
+/// Data: `[2][units: u32 LE]`.
+#[allow(dead_code)]
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data: Vec<u8> = vec![2];
+    data.extend(units.to_le_bytes());
+    Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID, accounts: Vec::new(), data }
+}
+
+/// ### Priority fee: price of one compute unit in micro-lamports (1e-6 lamport)
+/// Data: `[3][micro_lamports: u64 LE]`.
+#[allow(dead_code)]
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data: Vec<u8> = vec![3];
+    data.extend(micro_lamports.to_le_bytes());
+    Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID, accounts: Vec::new(), data }
+}
@@ -0,0 +1,31 @@
+use super::pubkey::Pubkey;
+
+/// ### Account passed to an instruction
+/// The order of the metas is the order of the program's `#[derive(Accounts)]` struct.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[allow(dead_code)]
+impl AccountMeta {
+    pub fn writable(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: true }
+    }
+
+    pub fn readonly(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: false }
+    }
+}
+
+/// ### One instruction of a transaction, before it's compiled into a message
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
@@ -0,0 +1,150 @@
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+use crate::rpc::{
+    error::RpcError,
+    types::MessageHeader,
+};
+use super::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+};
+
+/// ### Legacy (unversioned) message, compiled from instructions
+/// Account keys are deduplicated (flags are merged) and ordered the way the runtime expects:
+/// `[writable signers (payer first)][readonly signers][writable non-signers][readonly non-signers]`,
+/// instructions reference them by `u8` index, so a legacy message holds at most 256 keys.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub header: MessageHeader,
+    pub account_keys: Vec<Pubkey>,
+    pub recent_blockhash: [u8; 32],
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledInstruction {
+    pub program_id_index: u8,
+    pub accounts: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+#[allow(dead_code)]
+struct KeyFlags {
+    pubkey: Pubkey,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+#[allow(dead_code)]
+impl Message {
+    pub fn new(instructions: &[Instruction], payer: &Pubkey, recent_blockhash: [u8; 32]) -> Result<Self, RpcError> {
+        let mut keys: Vec<KeyFlags> = vec![KeyFlags { pubkey: *payer, is_signer: true, is_writable: true }];
+        let mut add = |pubkey: Pubkey, is_signer: bool, is_writable: bool| {
+            match keys.iter_mut().find(|key| key.pubkey == pubkey) {
+                Some(key) => {
+                    key.is_signer |= is_signer;
+                    key.is_writable |= is_writable;
+                },
+                None => keys.push(KeyFlags { pubkey, is_signer, is_writable }),
+            }
+        };
+        for instruction in instructions {
+            for meta in &instruction.accounts {
+                add(meta.pubkey, meta.is_signer, meta.is_writable);
+            }
+            add(instruction.program_id, false, false);
+        }
+
+        // stable, so the payer stays first and the rest keeps the order of appearance
+        keys.sort_by_key(|key| (!key.is_signer, !key.is_writable));
+
+        let count = |what: &str, f: fn(&KeyFlags) -> bool| -> Result<u8, RpcError> {
+            let count: usize = keys.iter().filter(|key| f(key)).count();
+            u8::try_from(count).map_err(|_| RpcError::InvalidRequest(format!("{count} {what} don't fit into a legacy message (max 255)")))
+        };
+        let header: MessageHeader = MessageHeader {
+            num_required_signatures: count("signers", |key| key.is_signer)?,
+            num_readonly_signed_accounts: count("readonly signers", |key| key.is_signer && !key.is_writable)?,
+            num_readonly_unsigned_accounts: count("readonly non-signers", |key| !key.is_signer && !key.is_writable)?,
+        };
+        let account_keys: Vec<Pubkey> = keys.into_iter().map(|key| key.pubkey).collect();
+
+        let index = |pubkey: &Pubkey| -> Result<u8, RpcError> {
+            let index: usize = account_keys.iter().position(|key| key == pubkey).expect("every key was added above");
+            u8::try_from(index).map_err(|_| {
+                RpcError::InvalidRequest(format!("{} account keys don't fit into a legacy message (max 256)", account_keys.len()))
+            })
+        };
+        let instructions: Vec<CompiledInstruction> = instructions
+            .iter()
+            .map(|instruction| {
+                Ok(CompiledInstruction {
+                    program_id_index: index(&instruction.program_id)?,
+                    accounts: instruction.accounts.iter().map(|meta| index(&meta.pubkey)).collect::<Result<_, _>>()?,
+                    data: instruction.data.clone(),
+                })
+            })
+            .collect::<Result<_, RpcError>>()?;
+
+        Ok(Self { header, account_keys, recent_blockhash, instructions })
+    }
+
+    /// Accounts the transaction write-locks, these decide which fee markets it competes in.
+    pub fn writable_accounts(&self) -> impl Iterator<Item = &Pubkey> {
+        let signers: usize = self.header.num_required_signatures.into();
+        let writable_signers: usize = signers - usize::from(self.header.num_readonly_signed_accounts);
+        let writable_unsigned: usize = self.account_keys.len() - signers - usize::from(self.header.num_readonly_unsigned_accounts);
+        self.account_keys[..writable_signers]
+            .iter()
+            .chain(&self.account_keys[signers..signers + writable_unsigned])
+    }
+
+    /// Wire format, the bytes that are signed. Inverse of the message part of `encoding::decode_transaction`.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![
+            self.header.num_required_signatures,
+            self.header.num_readonly_signed_accounts,
+            self.header.num_readonly_unsigned_accounts,
+        ];
+        write_short_len(&mut bytes, self.account_keys.len());
+        for key in &self.account_keys {
+            bytes.extend(key.as_bytes());
+        }
+        bytes.extend(self.recent_blockhash);
+        write_short_len(&mut bytes, self.instructions.len());
+        for instruction in &self.instructions {
+            bytes.push(instruction.program_id_index);
+            write_short_len(&mut bytes, instruction.accounts.len());
+            bytes.extend(&instruction.accounts);
+            write_short_len(&mut bytes, instruction.data.len());
+            bytes.extend(&instruction.data);
+        }
+        bytes
+    }
+
+    /// ### Transaction with zeroed signatures, base64
+    /// Only good for simulateTransaction with `sig_verify: false`, the node rejects it everywhere else.
+    pub fn to_unsigned_transaction_base64(&self) -> String {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_short_len(&mut bytes, self.header.num_required_signatures.into());
+        bytes.resize(bytes.len() + 64 * usize::from(self.header.num_required_signatures), 0);
+        bytes.extend(self.serialize());
+        BASE64.encode(bytes)
+    }
+}
+
+/// compact-u16 (shortvec), see `WireReader::short_len`.
+#[allow(dead_code)]
+fn write_short_len(bytes: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let byte: u8 = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
@@ -0,0 +1,8 @@
+// clients of our own on-chain programs, the rpc module stays program-agnostic
+
+pub mod compute_budget;
+pub mod instruction;
+pub mod message;
+pub mod priority_fee;
+pub mod pubkey;
+pub mod smart_contracts;
@@ -0,0 +1,152 @@
+use crate::rpc::{
+    client::RpcClient,
+    error::RpcError,
+    types::{PrioritizationFee, SimulateTransactionResult},
+};
+use super::{
+    compute_budget::{self, COMPUTE_BUDGET_PROGRAM_ID, MAX_COMPUTE_UNIT_LIMIT},
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PriorityFeeConfig {
+    pub percentile: u8,  // 0..=100 of the recent per-slot fees, higher => lands sooner, costs more
+    pub safety_margin_percent: u64,  // unit limit = units consumed + margin, state may change between simulation and execution
+    pub max_unit_price: Option<u64>,  // micro-lamports, congestion spikes shouldn't drain the payer
+}
+
+impl Default for PriorityFeeConfig {
+    fn default() -> Self {
+        Self {
+            percentile: 75,
+            safety_margin_percent: 10,
+            max_unit_price: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub unit_limit: u32,
+    pub unit_price: u64,  // micro-lamports per compute unit
+    pub units_consumed: u64,  // simulated, the compute-budget instructions included
+}
+
+#[allow(dead_code)]
+impl ComputeBudget {
+    /// Priority fee on top of the base fee (5000 lamports per signature), charged for the limit, not the units used.
+    /// The runtime caps the limit at `MAX_COMPUTE_UNIT_LIMIT`, an absurd price saturates at `u64::MAX`.
+    pub fn priority_fee_lamports(&self) -> u64 {
+        let unit_limit: u128 = self.unit_limit.min(MAX_COMPUTE_UNIT_LIMIT).into();
+        let fee: u128 = (unit_limit * u128::from(self.unit_price)).div_ceil(1_000_000);
+        u64::try_from(fee).unwrap_or(u64::MAX)
+    }
+
+    /// `SetComputeUnitLimit` + `SetComputeUnitPrice`, then the instructions. Compute-budget instructions already among
+    /// them are dropped, a transaction with two of the same kind fails.
+    pub fn prepend_to(&self, instructions: Vec<Instruction>) -> Vec<Instruction> {
+        [compute_budget::set_compute_unit_limit(self.unit_limit), compute_budget::set_compute_unit_price(self.unit_price)]
+            .into_iter()
+            .chain(without_compute_budget(instructions))
+            .collect()
+    }
+}
+
+/// ### Compute-budget / priority-fee estimation
+/// 1. Price: the `percentile` of getRecentPrioritizationFees over the accounts the transaction write-locks
+///    (the fee markets are per account, e.g. the `meta` PDA of `update_pda`).
+/// 2. Limit: simulateTransaction (unsigned, the node swaps in its blockhash) `unitsConsumed` + `safety_margin_percent`.
+///
+/// ```ignore
+/// let estimator = PriorityFeeEstimator::new(client);
+/// let instructions = estimator.with_compute_budget(vec![smart_contracts::update_pda(&signer)], &signer).await?;
+/// ```
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct PriorityFeeEstimator {
+    client: RpcClient,
+    config: PriorityFeeConfig,
+}
+
+#[allow(dead_code)]
+impl PriorityFeeEstimator {
+    pub fn new(client: RpcClient) -> Self {
+        Self { client, config: PriorityFeeConfig::default() }
+    }
+
+    pub fn with_config(mut self, config: PriorityFeeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Micro-lamports per compute unit to compete for the accounts.
+    pub async fn unit_price(&self, writable_accounts: &[Pubkey]) -> Result<u64, RpcError> {
+        let accounts: Vec<String> = writable_accounts.iter().map(Pubkey::to_string).collect();
+        let accounts: Vec<&str> = accounts.iter().map(String::as_str).collect();
+        let fees: Vec<PrioritizationFee> = self.client.get_recent_prioritization_fees(&accounts).await?;
+
+        let fees: Vec<u64> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+        let price: u64 = percentile(&fees, self.config.percentile);
+        Ok(self.config.max_unit_price.map_or(price, |max| price.min(max)))
+    }
+
+    /// Units the instructions (plus the two compute-budget instructions) consume, `Err(SimulationFailed)` if they fail.
+    pub async fn units_consumed(&self, instructions: &[Instruction], payer: &Pubkey) -> Result<u64, RpcError> {
+        // simulated with the max limit, the price instruction has the same cost whatever the price is
+        let instructions: Vec<Instruction> = ComputeBudget { unit_limit: MAX_COMPUTE_UNIT_LIMIT, unit_price: 0, units_consumed: 0 }
+            .prepend_to(instructions.to_vec());
+        let message: Message = Message::new(&instructions, payer, [0; 32])?;
+
+        let result: SimulateTransactionResult = self.client.simulate_transaction(&message.to_unsigned_transaction_base64()).await?;
+        if let Some(err) = result.err {
+            return Err(RpcError::SimulationFailed { err, logs: result.logs.unwrap_or_default() });
+        }
+        result.units_consumed.ok_or_else(|| RpcError::EmptyResult("simulateTransaction unitsConsumed".into()))
+    }
+
+    /// Price and limit for the instructions, both requests go out concurrently.
+    pub async fn estimate(&self, instructions: &[Instruction], payer: &Pubkey) -> Result<ComputeBudget, RpcError> {
+        let message: Message = Message::new(instructions, payer, [0; 32])?;
+        let writable_accounts: Vec<Pubkey> = message.writable_accounts().copied().collect();
+
+        let (unit_price, units_consumed) = tokio::try_join!(
+            self.unit_price(&writable_accounts),
+            self.units_consumed(instructions, payer),
+        )?;
+
+        let margin: u64 = self.config.safety_margin_percent.saturating_add(100);
+        let unit_limit: u64 = units_consumed.saturating_mul(margin).div_ceil(100);
+        let unit_limit: u32 = unit_limit.min(MAX_COMPUTE_UNIT_LIMIT.into()) as u32;
+        log::debug!("Compute budget: {units_consumed} CU consumed => limit {unit_limit}, price {unit_price} micro-lamports");
+
+        Ok(ComputeBudget { unit_limit, unit_price, units_consumed })
+    }
+
+    /// `estimate`, then `ComputeBudget::prepend_to`.
+    pub async fn with_compute_budget(&self, instructions: Vec<Instruction>, payer: &Pubkey) -> Result<Vec<Instruction>, RpcError> {
+        let instructions: Vec<Instruction> = without_compute_budget(instructions).collect();
+        let budget: ComputeBudget = self.estimate(&instructions, payer).await?;
+        Ok(budget.prepend_to(instructions))
+    }
+}
+
+/// Nearest-rank percentile, 0 for no fees (an idle market).
+#[allow(dead_code)]
+pub fn percentile(fees: &[u64], percentile: u8) -> u64 {
+    if fees.is_empty() {
+        return 0;
+    }
+    let mut fees: Vec<u64> = fees.to_vec();
+    fees.sort_unstable();
+    let rank: usize = (usize::from(percentile.min(100)) * fees.len()).div_ceil(100);
+    fees[rank.saturating_sub(1)]
+}
+
+#[allow(dead_code)]
+fn without_compute_budget(instructions: Vec<Instruction>) -> impl Iterator<Item = Instruction> {
+    instructions.into_iter().filter(|instruction| instruction.program_id != COMPUTE_BUDGET_PROGRAM_ID)
+}
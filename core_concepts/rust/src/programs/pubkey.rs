@@ -0,0 +1,102 @@
+use std::{fmt, str::FromStr};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use sha2::{Digest, Sha256};
+
+use crate::rpc::error::RpcError;
+
+/// Max number of seeds and max length of one seed, same limits as the runtime.
+#[allow(dead_code)]
+pub const MAX_SEEDS: usize = 16;
+#[allow(dead_code)]
+pub const MAX_SEED_LEN: usize = 32;
+
+#[allow(dead_code)]
+const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+/// ### 32-byte account address, base58 in RPC
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Pubkey(pub [u8; 32]);
+
+/// `11111111111111111111111111111111`
+#[allow(dead_code)]
+pub const SYSTEM_PROGRAM_ID: Pubkey = Pubkey([0; 32]);
+
+#[allow(dead_code)]
+impl Pubkey {
+    pub const fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Keypair addresses are points of ed25519, PDAs must NOT be (nobody can sign for them).
+    pub fn is_on_curve(&self) -> bool {
+        CompressedEdwardsY(self.0).decompress().is_some()
+    }
+
+    /// ### Program derived address for the exact seeds (the bump included)
+    /// `sha256(seeds || program_id || "ProgramDerivedAddress")`, `None` if the hash lands on the curve.
+    pub fn create_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Result<Option<Self>, RpcError> {
+        if seeds.len() > MAX_SEEDS {
+            return Err(RpcError::Decode(format!("Too many seeds: {}, max {MAX_SEEDS}", seeds.len())));
+        }
+        if let Some(seed) = seeds.iter().find(|seed| seed.len() > MAX_SEED_LEN) {
+            return Err(RpcError::Decode(format!("Seed is too long: {} bytes, max {MAX_SEED_LEN}", seed.len())));
+        }
+
+        let mut hasher: Sha256 = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update(program_id.0);
+        hasher.update(PDA_MARKER);
+        let address: Self = Self(hasher.finalize().into());
+
+        Ok((!address.is_on_curve()).then_some(address))
+    }
+
+    /// ### Canonical PDA: the first bump from 255 down, that gives an off-curve address
+    /// This is what Anchor's `seeds = [...], bump` derives and stores.
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Result<(Self, u8), RpcError> {
+        // one seed is taken by the bump
+        if seeds.len() >= MAX_SEEDS {
+            return Err(RpcError::Decode(format!("Too many seeds: {}, max {}", seeds.len(), MAX_SEEDS - 1)));
+        }
+
+        for bump in (0..=u8::MAX).rev() {
+            let bump_seed: [u8; 1] = [bump];
+            let seeds_with_bump: Vec<&[u8]> = seeds.iter().copied().chain(std::iter::once(&bump_seed[..])).collect();
+            if let Some(address) = Self::create_program_address(&seeds_with_bump, program_id)? {
+                return Ok((address, bump));
+            }
+        }
+        Err(RpcError::Decode(format!("No viable bump for the seeds under {program_id}")))
+    }
+}
+
+impl FromStr for Pubkey {
+    type Err = RpcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes: Vec<u8> = bs58::decode(s).into_vec().map_err(|e| RpcError::Decode(format!("Pubkey {s}: {e}")))?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| RpcError::Decode(format!("Pubkey {s} is {} bytes, expected 32", bytes.len())))?;
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bs58::encode(self.0).into_string())
+    }
+}
+
+impl fmt::Debug for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
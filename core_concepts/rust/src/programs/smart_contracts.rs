@@ -0,0 +1,112 @@
+use crate::rpc::{
+    anchor::AccountDeserialize,
+    client::RpcClient,
+    error::RpcError,
+    types::Account,
+};
+use super::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::{Pubkey, SYSTEM_PROGRAM_ID},
+};
+
+/// `declare_id!` of `core_concepts/smart_contracts`: wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5
+#[allow(dead_code)]
+pub const PROGRAM_ID: Pubkey = Pubkey::new([
+    13, 235, 144, 171, 13, 125, 92, 24, 175, 106, 33, 59, 206, 202, 236, 4,
+    0, 65, 161, 19, 251, 133, 239, 252, 195, 157, 101, 242, 224, 91, 242, 150,
+]);
+
+#[allow(dead_code)]
+pub const META_SEED: &[u8] = b"meta";
+
+// sha256("global:<instruction>")[..8]
+#[allow(dead_code)]
+pub const INIT_PDA_DISCRIMINATOR: [u8; 8] = [27, 252, 179, 86, 232, 226, 18, 157];
+#[allow(dead_code)]
+pub const UPDATE_PDA_DISCRIMINATOR: [u8; 8] = [229, 63, 241, 182, 220, 190, 61, 161];
+
+/// ### PDA created by `init_pda`, seeds: `[b"meta", signer]`
+/// Layout: `[discriminator: 8][counter: u64 LE][bump_seed: u8][padding: 7]`, `space = 8 + 16`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PDAmeta {
+    pub counter: u64,
+    pub bump_seed: u8,
+}
+
+#[allow(dead_code)]
+impl PDAmeta {
+    pub const LEN: usize = 8 + 16;
+}
+
+impl AccountDeserialize for PDAmeta {
+    // sha256("account:PDAmeta")[..8]
+    const DISCRIMINATOR: [u8; 8] = [89, 168, 156, 228, 203, 197, 212, 182];
+
+    fn deserialize(data: &[u8]) -> Result<Self, RpcError> {
+        let (counter, rest) = data
+            .split_first_chunk::<8>()
+            .ok_or_else(|| RpcError::Decode(format!("PDAmeta is too short: {} bytes", data.len())))?;
+        let bump_seed: u8 = *rest
+            .first()
+            .ok_or_else(|| RpcError::Decode(format!("PDAmeta is too short: {} bytes", data.len())))?;
+
+        Ok(Self { counter: u64::from_le_bytes(*counter), bump_seed })
+    }
+}
+
+/// Address and canonical bump of the signer's `PDAmeta`.
+#[allow(dead_code)]
+pub fn find_meta_address(signer: &Pubkey) -> (Pubkey, u8) {
+    // two short seeds can't fail the length checks
+    Pubkey::find_program_address(&[META_SEED, signer.as_bytes()], &PROGRAM_ID).expect("meta seeds are within the limits")
+}
+
+/// ### `init_pda`: creates the signer's `PDAmeta`, the signer pays the rent
+/// Accounts: `meta` (writable), `signer` (signer, writable), `system_program`.
+#[allow(dead_code)]
+pub fn init_pda(signer: &Pubkey) -> Instruction {
+    let (meta, _) = find_meta_address(signer);
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::writable(meta, false),
+            AccountMeta::writable(*signer, true),
+            AccountMeta::readonly(SYSTEM_PROGRAM_ID, false),
+        ],
+        data: INIT_PDA_DISCRIMINATOR.to_vec(),
+    }
+}
+
+/// ### `update_pda`: increments `counter` of the signer's `PDAmeta`
+/// Accounts: `meta` (writable), `signer` (signer), `system_program`.
+#[allow(dead_code)]
+pub fn update_pda(signer: &Pubkey) -> Instruction {
+    let (meta, _) = find_meta_address(signer);
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::writable(meta, false),
+            AccountMeta::readonly(*signer, true),
+            AccountMeta::readonly(SYSTEM_PROGRAM_ID, false),
+        ],
+        data: UPDATE_PDA_DISCRIMINATOR.to_vec(),
+    }
+}
+
+/// ### Current `PDAmeta` of the signer
+/// `Ok(None)` before `init_pda`. An account that isn't owned by the program is an error, not a `PDAmeta`.
+#[allow(dead_code)]
+pub async fn fetch_meta(client: &RpcClient, signer: &Pubkey) -> Result<Option<PDAmeta>, RpcError> {
+    let (address, _) = find_meta_address(signer);
+    let account: Option<Account> = client.get_account(&address.to_string()).await?;
+
+    account
+        .map(|account| {
+            if account.owner != PROGRAM_ID.to_string() {
+                return Err(RpcError::Decode(format!("{address} is owned by {}, not by {PROGRAM_ID}", account.owner)));
+            }
+            PDAmeta::try_from_account_data(&account.data)
+        })
+        .transpose()
+}
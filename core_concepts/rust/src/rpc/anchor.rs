@@ -0,0 +1,111 @@
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures_util::{Stream, StreamExt};
+use sha2::{Digest, Sha256};
+
+use super::{
+    error::RpcError,
+    types::AccountNotification,
+};
+
+/// ### Account type of an Anchor program
+/// Anchor prefixes account data with an 8-byte discriminator: `sha256("account:<StructName>")[..8]`,
+/// the rest is the borsh-serialized struct.
+#[allow(dead_code)]
+pub trait AccountDeserialize: Sized {
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Decodes the data AFTER the discriminator.
+    fn deserialize(data: &[u8]) -> Result<Self, RpcError>;
+
+    /// Checks the discriminator, then decodes the rest.
+    fn try_from_account_data(data: &[u8]) -> Result<Self, RpcError> {
+        match data.split_first_chunk::<8>() {
+            Some((discriminator, rest)) if *discriminator == Self::DISCRIMINATOR => Self::deserialize(rest),
+            Some((discriminator, _)) => Err(RpcError::Decode(format!("Discriminator mismatch: {discriminator:?}, expected {:?}", Self::DISCRIMINATOR))),
+            None => Err(RpcError::Decode(format!("Account data is too short for a discriminator: {} bytes", data.len()))),
+        }
+    }
+}
+
+/// `sha256("account:<name>")[..8]`, handy for checking hard-coded `DISCRIMINATOR`s.
+#[allow(dead_code)]
+pub fn account_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("account:{name}"));
+    let mut discriminator: [u8; 8] = [0; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// `sha256("global:<name>")[..8]`, prefix of the data of Anchor instruction `<name>` (snake_case, as in the program).
+#[allow(dead_code)]
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("global:{name}"));
+    let mut discriminator: [u8; 8] = [0; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Decoded account state at the slot of the notification.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedAccount<T> {
+    pub slot: u64,
+    pub lamports: u64,
+    pub account: T,
+}
+
+impl<T: AccountDeserialize> TryFrom<AccountNotification> for DecodedAccount<T> {
+    type Error = RpcError;
+
+    fn try_from(notification: AccountNotification) -> Result<Self, Self::Error> {
+        let data: Vec<u8> = notification.account.data.decode()?;
+        Ok(Self {
+            slot: notification.slot,
+            lamports: notification.account.lamports,
+            account: T::try_from_account_data(&data)?,
+        })
+    }
+}
+
+/// ### Typed view over any accountSubscribe stream
+/// Works with `Subscription`, `PubsubSubscription`, supervised and failover ones alike.
+/// The subscription has to use a binary encoding (e.g. base64), a notification that fails to decode is yielded as `Err`
+/// and the stream goes on.
+///
+/// ```ignore
+/// let pubsub = client.account_subscribe(pda, AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }).await?;
+/// let mut metas = decode_accounts::<PDAmeta, _>(pubsub);
+/// while let Some(meta) = metas.next().await { ... }
+/// ```
+#[allow(dead_code)]
+pub struct DecodedAccounts<S, T> {
+    inner: S,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[allow(dead_code)]
+pub fn decode_accounts<T, S>(inner: S) -> DecodedAccounts<S, T>
+where
+    T: AccountDeserialize,
+    S: Stream<Item = Result<AccountNotification, RpcError>> + Unpin
+{
+    DecodedAccounts { inner, _marker: PhantomData }
+}
+
+impl<S, T> Stream for DecodedAccounts<S, T>
+where
+    T: AccountDeserialize,
+    S: Stream<Item = Result<AccountNotification, RpcError>> + Unpin
+{
+    type Item = Result<DecodedAccount<T>, RpcError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner
+            .poll_next_unpin(cx)
+            .map(|item| item.map(|notification| notification.and_then(DecodedAccount::try_from)))
+    }
+}
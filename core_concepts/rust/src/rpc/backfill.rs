@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use futures_util::{Stream, StreamExt, stream};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::RpcClient,
+    config::{SignaturesForAddressConfig, TransactionConfig, TxCommitment},
+    error::RpcError,
+    types::{EncodedTransactionWithMeta, SignatureInfo},
+};
+
+/// Max `limit` of getSignaturesForAddress.
+#[allow(dead_code)]
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// ### Position of a backfill, meant to be persisted between runs
+/// - `before`: oldest signature already processed, the next page starts right after it (exclusive).
+/// - `until`: the history is walked back to this signature (exclusive), e.g. `newest` of the previous full run.
+/// - `newest`: first signature ever returned by this backfill, becomes `until` of the next incremental run.
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackfillCursor {
+    pub before: Option<String>,
+    pub until: Option<String>,
+    pub newest: Option<String>,
+}
+
+/// ### Walks the signature history of an address, newest -> oldest
+/// One getSignaturesForAddress per page. It goes through `RpcClient`, not the bare request helpers of `communication`:
+/// a long backfill needs the client's pooled connections, retries and rate limit, which `communication` doesn't have.
+/// The cursor moves only after the whole page was returned, so persisting `cursor()` after each processed page
+/// and passing it to `resume_from` after a crash never skips a signature (at worst the last page is processed twice).
+///
+/// ```ignore
+/// let mut backfill = SignatureBackfill::new(client, &PROGRAM_ID.to_string()).resume_from(load_cursor()?);
+/// while let Some(page) = backfill.next_page_with_transactions(8).await? {
+///     store(page)?;
+///     save_cursor(backfill.cursor())?;
+/// }
+/// ```
+#[allow(dead_code)]
+pub struct SignatureBackfill {
+    client: RpcClient,
+    address: String,
+    cursor: BackfillCursor,
+    page_limit: usize,
+    commitment: Option<TxCommitment>,
+    transaction_config: TransactionConfig,
+    done: bool,
+}
+
+#[allow(dead_code)]
+impl SignatureBackfill {
+    pub fn new(client: RpcClient, address: &str) -> Self {
+        Self {
+            client,
+            address: address.to_string(),
+            cursor: BackfillCursor::default(),
+            page_limit: MAX_PAGE_LIMIT,
+            commitment: None,
+            transaction_config: TransactionConfig::default(),
+            done: false,
+        }
+    }
+
+    /// Signatures per request, clamped to 1..=1000.
+    pub fn page_limit(mut self, limit: usize) -> Self {
+        self.page_limit = limit.clamp(1, MAX_PAGE_LIMIT);
+        self
+    }
+
+    /// Stops at this signature (exclusive), e.g. the newest one of the previous run.
+    pub fn until(mut self, signature: &str) -> Self {
+        self.cursor.until = Some(signature.to_string());
+        self
+    }
+
+    pub fn resume_from(mut self, cursor: BackfillCursor) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn commitment(mut self, commitment: TxCommitment) -> Self {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    /// Used by the `*_with_transactions` methods.
+    pub fn transaction_config(mut self, config: TransactionConfig) -> Self {
+        self.transaction_config = config;
+        self
+    }
+
+    pub fn cursor(&self) -> &BackfillCursor {
+        &self.cursor
+    }
+
+    /// ### Next (older) page of signatures
+    /// `Ok(None)` once the history (or `until`) is reached. On `Err` the cursor stays, so the call can be repeated.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<SignatureInfo>>, RpcError> {
+        let page: Vec<SignatureInfo> = match self.fetch_page().await? {
+            Some(page) => page,
+            None => return Ok(None),
+        };
+        self.advance(&page);
+        Ok(Some(page))
+    }
+
+    /// ### Next page with full transactions
+    /// Up to `concurrency` getTransaction requests are in flight, the order of the page is kept.
+    /// `None` transaction => the node doesn't have it (anymore), e.g. it's out of the provider's history.
+    pub async fn next_page_with_transactions(
+        &mut self,
+        concurrency: usize
+    ) -> Result<Option<Vec<(SignatureInfo, Option<EncodedTransactionWithMeta>)>>, RpcError> {
+        let page: Vec<SignatureInfo> = match self.fetch_page().await? {
+            Some(page) => page,
+            None => return Ok(None),
+        };
+
+        let transactions: Vec<Option<EncodedTransactionWithMeta>> = stream::iter(page.iter())
+            .map(|info| self.client.get_transaction_with_config(&info.signature, self.transaction_config.clone()))
+            .buffered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, RpcError>>()?;
+
+        // the cursor moves only when the whole page is fetched
+        self.advance(&page);
+        Ok(Some(page.into_iter().zip(transactions).collect()))
+    }
+
+    /// Every signature as a stream, the first error ends it.
+    pub fn into_stream(self) -> impl Stream<Item = Result<SignatureInfo, RpcError>> {
+        stream::unfold((self, VecDeque::new()), |(mut backfill, mut buffer)| async move {
+            loop {
+                if let Some(info) = buffer.pop_front() {
+                    return Some((Ok(info), (backfill, buffer)));
+                }
+                match backfill.next_page().await {
+                    Ok(Some(page)) => buffer.extend(page),
+                    Ok(None) => return None,
+                    Err(e) => {
+                        backfill.done = true;
+                        return Some((Err(e), (backfill, buffer)));
+                    },
+                }
+            }
+        })
+    }
+
+    /// Same as `into_stream`, but every signature comes with its transaction, see `next_page_with_transactions`.
+    pub fn into_stream_with_transactions(
+        self,
+        concurrency: usize
+    ) -> impl Stream<Item = Result<(SignatureInfo, Option<EncodedTransactionWithMeta>), RpcError>> {
+        stream::unfold((self, VecDeque::new()), move |(mut backfill, mut buffer)| async move {
+            loop {
+                if let Some(item) = buffer.pop_front() {
+                    return Some((Ok(item), (backfill, buffer)));
+                }
+                match backfill.next_page_with_transactions(concurrency).await {
+                    Ok(Some(page)) => buffer.extend(page),
+                    Ok(None) => return None,
+                    Err(e) => {
+                        backfill.done = true;
+                        return Some((Err(e), (backfill, buffer)));
+                    },
+                }
+            }
+        })
+    }
+
+    async fn fetch_page(&mut self) -> Result<Option<Vec<SignatureInfo>>, RpcError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let config: SignaturesForAddressConfig = SignaturesForAddressConfig {
+            commitment: self.commitment,
+            before: self.cursor.before.clone(),
+            until: self.cursor.until.clone(),
+            limit: Some(self.page_limit),
+            ..Default::default()
+        };
+        let page: Vec<SignatureInfo> = self.client.get_signatures_for_address(&self.address, config).await?;
+
+        if page.is_empty() {
+            self.done = true;
+            return Ok(None);
+        }
+        Ok(Some(page))
+    }
+
+    // a short page doesn't mean the end: providers cap `limit` and may return short pages mid-history,
+    // only an empty page does (see `fetch_page`)
+    fn advance(&mut self, page: &[SignatureInfo]) {
+        if self.cursor.newest.is_none() {
+            self.cursor.newest = page.first().map(|info| info.signature.clone());
+        }
+        if let Some(oldest) = page.last() {
+            self.cursor.before = Some(oldest.signature.clone());
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+};
+use serde::de::DeserializeOwned;
+
+use super::{
+    client::RpcClient,
+    config::{
+        AccountInfoConfig,
+        ContextConfig,
+        SignaturesForAddressConfig,
+        TransactionConfig,
+    },
+    error::RpcError,
+    types::{
+        RpcResponse,
+        RpcContextResponse,
+        EncodedTransactionWithMeta,
+        UiAccount,
+        LatestBlockhash,
+        SignatureInfo,
+    },
+};
+
+/// ### JSON-RPC batch
+/// Queues requests of different methods and sends them as a single JSON array in ONE HTTP POST.
+/// The node may answer in any order, so responses are matched back by `id`.
+///
+/// ```ignore
+/// let mut batch = client.batch();
+/// let handles = sigs.iter().map(|sig| batch.get_transaction(sig, TransactionConfig::default())).collect::<Vec<_>>();
+/// let slot = batch.get_slot(ContextConfig::default());
+/// let mut res = batch.send().await?;  // fails only if the whole POST failed
+/// let slot: Result<u64, RpcError> = res.take(slot);
+/// ```
+#[allow(dead_code)]
+pub struct Batch<'a> {
+    client: &'a RpcClient,
+    requests: Vec<serde_json::Value>,
+}
+
+/// Typed ticket for a single request in the batch, redeemed with `BatchResponse::take`.
+#[allow(dead_code)]
+pub struct BatchHandle<T> {
+    id: u64,
+    method: &'static str,
+    decode: fn(&'static str, Option<serde_json::Value>) -> Result<T, RpcError>,
+    _marker: PhantomData<T>,
+}
+
+/// Responses of the batch, indexed by request `id`.
+#[allow(dead_code)]
+pub struct BatchResponse {
+    responses: HashMap<u64, RpcResponse<serde_json::Value>>,
+}
+
+#[allow(dead_code)]
+impl<'a> Batch<'a> {
+    pub(crate) fn new(client: &'a RpcClient) -> Self {
+        Self { client, requests: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Queues any method. `null` result is decoded as `None`.
+    pub fn add<T: DeserializeOwned>(&mut self, method: &'static str, params: serde_json::Value) -> BatchHandle<Option<T>> {
+        self.push(method, params, decode_optional::<T>)
+    }
+
+    pub fn get_account_info(&mut self, pubkey: &str, config: AccountInfoConfig) -> BatchHandle<Option<UiAccount>> {
+        let params: serde_json::Value = self.client.get_account_info_params(pubkey, config);
+        self.push("getAccountInfo", params, decode_context::<Option<UiAccount>>)
+    }
+
+    pub fn get_balance(&mut self, pubkey: &str, config: ContextConfig) -> BatchHandle<u64> {
+        let params: serde_json::Value = self.client.get_balance_params(pubkey, config);
+        self.push("getBalance", params, decode_context::<u64>)
+    }
+
+    pub fn get_slot(&mut self, config: ContextConfig) -> BatchHandle<u64> {
+        let params: serde_json::Value = self.client.context_params(config);
+        self.push("getSlot", params, decode_expect::<u64>)
+    }
+
+    pub fn get_latest_blockhash(&mut self, config: ContextConfig) -> BatchHandle<LatestBlockhash> {
+        let params: serde_json::Value = self.client.context_params(config);
+        self.push("getLatestBlockhash", params, decode_context::<LatestBlockhash>)
+    }
+
+    pub fn get_signatures_for_address(&mut self, address: &str, config: SignaturesForAddressConfig) -> BatchHandle<Vec<SignatureInfo>> {
+        let params: serde_json::Value = self.client.get_signatures_for_address_params(address, config);
+        self.push("getSignaturesForAddress", params, decode_expect::<Vec<SignatureInfo>>)
+    }
+
+    pub fn get_transaction(&mut self, signature: &str, config: TransactionConfig) -> BatchHandle<Option<EncodedTransactionWithMeta>> {
+        let params: serde_json::Value = self.client.get_transaction_params(signature, config);
+        self.push("getTransaction", params, decode_optional::<EncodedTransactionWithMeta>)
+    }
+
+    fn push<T>(
+        &mut self,
+        method: &'static str,
+        params: serde_json::Value,
+        decode: fn(&'static str, Option<serde_json::Value>) -> Result<T, RpcError>
+    ) -> BatchHandle<T> {
+        let id: u64 = self.client.next_id();
+        self.requests.push(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        }));
+        BatchHandle { id, method, decode, _marker: PhantomData }
+    }
+
+    /// ### Sends the whole batch in one POST
+    /// `Err` means the batch as a whole failed (transport, HTTP status, node rejected the batch).
+    /// Per-request errors are returned by `BatchResponse::take`.
+    pub async fn send(self) -> Result<BatchResponse, RpcError> {
+        if self.requests.is_empty() {
+            return Ok(BatchResponse { responses: HashMap::new() });
+        }
+
+        let request_json_rpc: serde_json::Value = serde_json::Value::Array(self.requests);
+        let res_body: String = self.client.with_retry(|| self.client.post(&request_json_rpc)).await?;
+
+        // a node that rejects the batch itself (e.g. too large) answers with a single error object instead of an array
+        let responses: Vec<RpcResponse<serde_json::Value>> = match serde_json::from_str(&res_body) {
+            Ok(responses) => responses,
+            Err(e) => {
+                let res_json_rpc: RpcResponse<serde_json::Value> = serde_json::from_str(&res_body).map_err(|_| e)?;
+                return Err(match res_json_rpc.error {
+                    Some(e) => e.into(),
+                    None => RpcError::InvalidRequest("Expected an array of responses for the batch request!".into()),
+                });
+            }
+        };
+
+        let responses: HashMap<u64, RpcResponse<serde_json::Value>> = responses
+            .into_iter()
+            .filter_map(|res| res.id.map(|id| (id, res)))
+            .collect();
+
+        Ok(BatchResponse { responses })
+    }
+}
+
+#[allow(dead_code)]
+impl BatchResponse {
+    /// Extracts the result of the request, that the handle was issued for.
+    pub fn take<T>(&mut self, handle: BatchHandle<T>) -> Result<T, RpcError> {
+        let res: RpcResponse<serde_json::Value> = self.responses
+            .remove(&handle.id)
+            .ok_or(RpcError::MissingBatchResponse(handle.id))?;
+
+        if let Some(e) = res.error {
+            return Err(e.into());
+        }
+        (handle.decode)(handle.method, res.result)
+    }
+}
+
+#[allow(dead_code)]
+fn decode_optional<T: DeserializeOwned>(_method: &'static str, result: Option<serde_json::Value>) -> Result<Option<T>, RpcError> {
+    Ok(result.map(serde_json::from_value).transpose()?)
+}
+
+#[allow(dead_code)]
+fn decode_expect<T: DeserializeOwned>(method: &'static str, result: Option<serde_json::Value>) -> Result<T, RpcError> {
+    let result: serde_json::Value = result.ok_or_else(|| RpcError::EmptyResult(method.to_string()))?;
+    Ok(serde_json::from_value(result)?)
+}
+
+#[allow(dead_code)]
+fn decode_context<T: DeserializeOwned>(method: &'static str, result: Option<serde_json::Value>) -> Result<T, RpcError> {
+    let res: RpcContextResponse<T> = decode_expect(method, result)?;
+    Ok(res.value)
+}
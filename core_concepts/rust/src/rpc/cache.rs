@@ -0,0 +1,198 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use sha2::{Digest, Sha256};
+
+use super::error::RpcError;
+
+/// Suffix of the temp files, see `ResponseCache::write_disk`.
+static TMP_WRITES: AtomicU64 = AtomicU64::new(0);
+
+/// Methods, whose `finalized` answers never change.
+pub const CACHEABLE_METHODS: [&str; 2] = ["getTransaction", "getBlock"];
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub capacity: usize,  // in-memory entries, least recently used ones are evicted
+    pub disk_dir: Option<PathBuf>,  // one file per response, survives restarts
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            disk_dir: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub disk_hits: u64,  // part of `hits`, that came from the disk store
+    pub misses: u64,
+    pub entries: usize,  // in memory
+}
+
+/// ### Cache of immutable responses
+/// Only `CACHEABLE_METHODS` requested with `"commitment": "finalized"` are cached, and only non-null results
+/// (a transaction, that isn't found yet, may still land). Keyed by method + params, so different encodings
+/// or `maxSupportedTransactionVersion` are different entries.
+///
+/// Cloning is cheap, clones share the entries and the counters. Batches bypass the cache.
+///
+/// ```ignore
+/// let cache = ResponseCache::new(CacheConfig { disk_dir: Some("rpc-cache".into()), ..Default::default() })?;
+/// let client = RpcClient::new_with_commitment(url, CommitmentLevel::Finalized)?.with_cache(cache.clone());
+/// SignatureBackfill::new(client, address).into_stream_with_transactions(8) ...;
+/// log::info!("{:?}", cache.stats());
+/// ```
+#[derive(Clone)]
+pub struct ResponseCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    memory: Mutex<Lru>,
+    disk_dir: Option<PathBuf>,
+    hits: AtomicU64,
+    disk_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[allow(dead_code)]
+impl ResponseCache {
+    /// Creates `disk_dir` if it doesn't exist.
+    pub fn new(config: CacheConfig) -> Result<Self, RpcError> {
+        if let Some(dir) = &config.disk_dir {
+            std::fs::create_dir_all(dir).map_err(|e| RpcError::Cache(format!("{}: {e}", dir.display())))?;
+        }
+        Ok(Self {
+            inner: Arc::new(Inner {
+                memory: Mutex::new(Lru::new(config.capacity)),
+                disk_dir: config.disk_dir,
+                hits: AtomicU64::new(0),
+                disk_hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            disk_hits: self.inner.disk_hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            entries: self.inner.memory.lock().unwrap().entries.len(),
+        }
+    }
+
+    /// `None` => the request must not be cached. The config object is the last element of `params`.
+    pub(crate) fn key(method: &str, params: &serde_json::Value) -> Option<String> {
+        let finalized: bool = params
+            .as_array()
+            .and_then(|params| params.last())
+            .is_some_and(|config| config["commitment"] == "finalized");
+        (finalized && CACHEABLE_METHODS.contains(&method)).then(|| format!("{method}:{params}"))
+    }
+
+    /// Memory first, then the disk (a disk hit is promoted to memory). Counts the hit / miss.
+    pub(crate) fn get(&self, key: &str) -> Option<Arc<serde_json::Value>> {
+        let cached: Option<Arc<serde_json::Value>> = self.inner.memory.lock().unwrap().get(key);
+        if let Some(value) = cached {
+            self.inner.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(value);
+        }
+
+        if let Some(value) = self.read_disk(key) {
+            let value: Arc<serde_json::Value> = Arc::new(value);
+            self.inner.memory.lock().unwrap().put(key.to_string(), value.clone());
+            self.inner.hits.fetch_add(1, Ordering::Relaxed);
+            self.inner.disk_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(value);
+        }
+
+        self.inner.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    pub(crate) fn put(&self, key: String, value: serde_json::Value) {
+        self.write_disk(&key, &value);
+        self.inner.memory.lock().unwrap().put(key, Arc::new(value));
+    }
+
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        let hash = Sha256::digest(key);
+        let name: String = hash.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.inner.disk_dir.as_ref().map(|dir| dir.join(format!("{name}.json")))
+    }
+
+    // disk failures are only logged, the cache is an optimization
+
+    fn read_disk(&self, key: &str) -> Option<serde_json::Value> {
+        let path: PathBuf = self.disk_path(key)?;
+        let bytes: Vec<u8> = std::fs::read(&path).ok()?;
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| log::warn!("Corrupted cache entry {}: {e}", path.display()))
+            .ok()
+    }
+
+    fn write_disk(&self, key: &str, value: &serde_json::Value) {
+        let Some(path) = self.disk_path(key) else { return; };
+        // written aside and renamed, so a crash never leaves a truncated entry behind.
+        // the temp name is unique per write: concurrent writers of the same key (threads or processes sharing the dir)
+        // would otherwise rename each other's half-written file
+        let write: u64 = TMP_WRITES.fetch_add(1, Ordering::Relaxed);
+        let tmp: PathBuf = path.with_extension(format!("{}.{write}.tmp", std::process::id()));
+        let written: std::io::Result<()> = std::fs::write(&tmp, value.to_string()).and_then(|_| std::fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&tmp);
+            log::warn!("Failed to store cache entry {}: {e}", path.display());
+        }
+    }
+}
+
+/// Least recently used entry is evicted first, `order` maps the last use (a tick) to the key.
+struct Lru {
+    entries: HashMap<String, (Arc<serde_json::Value>, u64)>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    capacity: usize,
+}
+
+#[allow(dead_code)]
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), order: BTreeMap::new(), tick: 0, capacity: capacity.max(1) }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Arc<serde_json::Value>> {
+        self.tick += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.to_string());
+        Some(value.clone())
+    }
+
+    fn put(&mut self, key: String, value: Arc<serde_json::Value>) {
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, key);
+
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else { break; };
+            self.entries.remove(&oldest);
+        }
+    }
+}
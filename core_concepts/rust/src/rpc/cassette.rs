@@ -0,0 +1,523 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    future::Future,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use futures_util::{Sink, Stream};
+use serde::{Deserialize, Serialize};
+use tokio::time::{Instant, Sleep};
+use tokio_tungstenite::{
+    MaybeTlsStream,
+    WebSocketStream,
+    connect_async,
+    tungstenite::{
+        self,
+        protocol::{CloseFrame, Message},
+    },
+};
+
+use super::error::RpcError;
+
+/// ### One line of a cassette file
+/// `t_ms` is the time since the recording started.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CassetteEntry {
+    pub t_ms: u64,
+    #[serde(flatten)]
+    pub event: CassetteEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CassetteEvent {
+    /// JSON-RPC request (single or batch) and the raw HTTP response
+    Http {
+        request: serde_json::Value,
+        status: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_secs: Option<u64>,
+        body: String,
+    },
+    /// WebSocket handshake, `error` if it failed
+    WsConnect {
+        conn: u64,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// Inbound frame
+    WsFrame { conn: u64, frame: WsFrame },
+    /// The connection broke (reset, protocol error, ...)
+    WsError { conn: u64, error: String },
+    /// The stream ended
+    WsEnd { conn: u64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WsFrame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+/// ### Record / replay of RPC traffic
+/// - `record`: every HTTP request/response of `RpcClient::with_cassette` and every inbound frame of the WebSocket clients
+///   (`PubsubClient::connect_with_cassette`, `SubscriptionTarget::with_cassette`) is appended to a JSON-lines file.
+/// - `replay`: the same clients are served from the file, nothing goes to the network. HTTP requests are matched by
+///   their content (ids aside) in the recorded order, a request recorded fewer times than it's replayed gets the last
+///   response again. WebSocket connections are matched by url in the recorded order, frames arrive at the same offsets
+///   from the connect as they did, drops and failed handshakes included, so reconnect logic sees the same timeline.
+///
+/// Transport errors of HTTP requests (connection refused, timeouts) aren't recorded, outgoing frames aren't either.
+#[derive(Clone)]
+pub struct Cassette {
+    mode: Arc<Mode>,
+}
+
+#[allow(dead_code)]
+enum Mode {
+    Record(Recorder),
+    Replay(Player),
+}
+
+#[allow(dead_code)]
+struct Recorder {
+    start: Instant,
+    file: Mutex<BufWriter<File>>,
+    next_conn: AtomicU64,
+}
+
+#[allow(dead_code)]
+struct Player {
+    http: Mutex<Vec<RecordedHttp>>,
+    connections: Mutex<Vec<RecordedConnection>>,
+}
+
+struct RecordedHttp {
+    request: serde_json::Value,  // ids removed
+    original: serde_json::Value,
+    status: u16,
+    retry_after_secs: Option<u64>,
+    body: String,
+    used: bool,
+}
+
+#[allow(dead_code)]
+struct RecordedConnection {
+    url: String,
+    connected_ms: u64,
+    error: Option<String>,
+    items: Vec<(u64, ReplayItem)>,
+    used: bool,
+}
+
+#[allow(dead_code)]
+enum ReplayItem {
+    Frame(Message),
+    Error(String),
+    End,
+}
+
+#[allow(dead_code)]
+impl Cassette {
+    /// Creates (truncates) the file.
+    pub fn record<P: AsRef<Path>>(path: P) -> Result<Self, RpcError> {
+        let file: File = File::create(path.as_ref()).map_err(|e| RpcError::Cassette(format!("{}: {e}", path.as_ref().display())))?;
+        Ok(Self {
+            mode: Arc::new(Mode::Record(Recorder {
+                start: Instant::now(),
+                file: Mutex::new(BufWriter::new(file)),
+                next_conn: AtomicU64::new(0),
+            })),
+        })
+    }
+
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Self, RpcError> {
+        let file: File = File::open(path.as_ref()).map_err(|e| RpcError::Cassette(format!("{}: {e}", path.as_ref().display())))?;
+        let entries: Vec<CassetteEntry> = BufReader::new(file)
+            .lines()
+            .map(|line| line.map_err(|e| RpcError::Cassette(e.to_string())))
+            .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Result<_, RpcError>>()?;
+        Ok(Self::replay_entries(entries))
+    }
+
+    pub fn replay_entries(entries: Vec<CassetteEntry>) -> Self {
+        let mut http: Vec<RecordedHttp> = Vec::new();
+        let mut connections: Vec<(u64, RecordedConnection)> = Vec::new();
+
+        for entry in entries {
+            let mut push = |conn: u64, item: ReplayItem| match connections.iter_mut().find(|(id, _)| *id == conn) {
+                Some((_, connection)) => connection.items.push((entry.t_ms.saturating_sub(connection.connected_ms), item)),
+                None => log::warn!("Cassette: frame of unknown connection {conn}"),
+            };
+            match entry.event {
+                CassetteEvent::Http { request, status, retry_after_secs, body } => http.push(RecordedHttp {
+                    request: without_ids(&request),
+                    original: request,
+                    status,
+                    retry_after_secs,
+                    body,
+                    used: false,
+                }),
+                CassetteEvent::WsConnect { conn, url, error } => connections.push((conn, RecordedConnection {
+                    url,
+                    connected_ms: entry.t_ms,
+                    error,
+                    items: Vec::new(),
+                    used: false,
+                })),
+                CassetteEvent::WsFrame { conn, frame } => push(conn, ReplayItem::Frame(frame.into())),
+                CassetteEvent::WsError { conn, error } => push(conn, ReplayItem::Error(error)),
+                CassetteEvent::WsEnd { conn } => push(conn, ReplayItem::End),
+            }
+        }
+
+        Self {
+            mode: Arc::new(Mode::Replay(Player {
+                http: Mutex::new(http),
+                connections: Mutex::new(connections.into_iter().map(|(_, connection)| connection).collect()),
+            })),
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(*self.mode, Mode::Replay(_))
+    }
+
+    /// ### HTTP hook of `RpcClient::post`
+    /// `network` is awaited only when recording.
+    pub(crate) async fn http<F>(&self, request: &serde_json::Value, network: F) -> Result<String, RpcError>
+    where
+        F: Future<Output = Result<String, RpcError>>
+    {
+        match &*self.mode {
+            Mode::Replay(player) => player.http(request),
+            Mode::Record(recorder) => {
+                let res: Result<String, RpcError> = network.await;
+                let recorded: Option<(u16, Option<u64>, &str)> = match &res {
+                    Ok(body) => Some((200, None, body)),
+                    Err(RpcError::HttpStatus { status, retry_after, body }) => Some((*status, retry_after.map(|d| d.as_secs()), body)),
+                    Err(_) => None,
+                };
+                if let Some((status, retry_after_secs, body)) = recorded {
+                    recorder.write(CassetteEvent::Http { request: request.clone(), status, retry_after_secs, body: body.to_string() });
+                }
+                res
+            },
+        }
+    }
+}
+
+impl Recorder {
+    fn write(&self, event: CassetteEvent) {
+        let entry: CassetteEntry = CassetteEntry { t_ms: self.start.elapsed().as_millis() as u64, event };
+        let mut file = self.file.lock().unwrap();
+        // flushed line by line, so a crashed run still leaves a usable cassette
+        let written: std::io::Result<()> = serde_json::to_writer(&mut *file, &entry)
+            .map_err(std::io::Error::from)
+            .and_then(|_| file.write_all(b"\n"))
+            .and_then(|_| file.flush());
+        if let Err(e) = written {
+            log::error!("Failed to write the cassette: {e}");
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Player {
+    fn http(&self, request: &serde_json::Value) -> Result<String, RpcError> {
+        let key: serde_json::Value = without_ids(request);
+        let mut http = self.http.lock().unwrap();
+        let recorded: &mut RecordedHttp = match http.iter().position(|recorded| !recorded.used && recorded.request == key) {
+            Some(i) => &mut http[i],
+            None => http
+                .iter_mut()
+                .rev()
+                .find(|recorded| recorded.request == key)
+                .ok_or_else(|| RpcError::Cassette(format!("No recorded response for {key}")))?,
+        };
+        recorded.used = true;
+
+        if !(200..300).contains(&recorded.status) {
+            return Err(RpcError::HttpStatus {
+                status: recorded.status,
+                retry_after: recorded.retry_after_secs.map(Duration::from_secs),
+                body: recorded.body.clone(),
+            });
+        }
+        Ok(with_ids_of(&recorded.body, &recorded.original, request))
+    }
+
+    fn connect(&self, url: &str) -> Result<ReplayConnection, RpcError> {
+        let mut connections = self.connections.lock().unwrap();
+        let connection: &mut RecordedConnection = connections
+            .iter_mut()
+            .find(|connection| !connection.used && connection.url == url)
+            .ok_or_else(|| RpcError::Cassette(format!("No recorded connection to {url} left")))?;
+        connection.used = true;
+
+        if let Some(error) = &connection.error {
+            let e: std::io::Error = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, error.clone());
+            return Err(RpcError::Handshake(Box::new(tungstenite::Error::Io(e))));
+        }
+        Ok(ReplayConnection {
+            items: std::mem::take(&mut connection.items)
+                .into_iter()
+                .map(|(offset, item)| (Duration::from_millis(offset), item))
+                .collect(),
+            start: Instant::now(),
+            sleep: None,
+            closed: false,
+            waker: None,
+        })
+    }
+}
+
+/// The `id` of a JSON-RPC request differs between runs, everything else has to match.
+fn without_ids(request: &serde_json::Value) -> serde_json::Value {
+    let mut request: serde_json::Value = request.clone();
+    match &mut request {
+        serde_json::Value::Array(batch) => {
+            for item in batch.iter_mut().filter_map(serde_json::Value::as_object_mut) {
+                item.remove("id");
+            }
+        },
+        serde_json::Value::Object(single) => {
+            single.remove("id");
+        },
+        _ => {},
+    }
+    request
+}
+
+/// Recorded response with the ids of the current request, batches are matched back by id (see `Batch`).
+fn with_ids_of(body: &str, recorded: &serde_json::Value, request: &serde_json::Value) -> String {
+    let Ok(mut response) = serde_json::from_str::<serde_json::Value>(body) else { return body.to_string(); };
+    match (&mut response, recorded, request) {
+        (serde_json::Value::Array(responses), serde_json::Value::Array(recorded), serde_json::Value::Array(request)) => {
+            for response in responses {
+                let position: Option<usize> = recorded.iter().position(|item| item["id"] == response["id"]);
+                if let Some(id) = position.and_then(|i| request.get(i)).map(|item| item["id"].clone()) {
+                    response["id"] = id;
+                }
+            }
+        },
+        (serde_json::Value::Object(_), _, serde_json::Value::Object(request)) => {
+            response["id"] = request.get("id").cloned().unwrap_or_default();
+        },
+        _ => {},
+    }
+    response.to_string()
+}
+
+impl From<WsFrame> for Message {
+    fn from(frame: WsFrame) -> Self {
+        match frame {
+            WsFrame::Text(text) => Message::text(text),
+            WsFrame::Binary(data) => Message::Binary(data.into()),
+            WsFrame::Ping(data) => Message::Ping(data.into()),
+            WsFrame::Pong(data) => Message::Pong(data.into()),
+            WsFrame::Close(frame) => Message::Close(frame.map(|(code, reason)| CloseFrame { code: code.into(), reason: reason.into() })),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl WsFrame {
+    fn from_message(message: &Message) -> Option<Self> {
+        Some(match message {
+            Message::Text(text) => Self::Text(text.to_string()),
+            Message::Binary(data) => Self::Binary(data.to_vec()),
+            Message::Ping(data) => Self::Ping(data.to_vec()),
+            Message::Pong(data) => Self::Pong(data.to_vec()),
+            Message::Close(frame) => Self::Close(frame.as_ref().map(|frame| (frame.code.into(), frame.reason.to_string()))),
+            Message::Frame(_) => return None,
+        })
+    }
+}
+
+/// ### Bidirectional WebSocket, whatever is behind it
+/// Network socket, recorded network socket or a replayed one, the readers can't tell them apart.
+#[allow(dead_code)]
+pub(crate) trait WsTransport:
+    Stream<Item = Result<Message, tungstenite::Error>> + Sink<Message, Error = tungstenite::Error> + Send + Unpin {}
+
+impl<T> WsTransport for T
+where
+    T: Stream<Item = Result<Message, tungstenite::Error>> + Sink<Message, Error = tungstenite::Error> + Send + Unpin {}
+
+#[allow(dead_code)]
+pub(crate) type WsConnection = Box<dyn WsTransport>;
+
+/// ### WebSocket handshake, through the cassette if there is one
+#[allow(dead_code)]
+pub(crate) async fn connect_ws(url: &str, cassette: Option<&Cassette>) -> Result<WsConnection, RpcError> {
+    let recorder: Option<(&Cassette, &Recorder)> = match cassette.map(|cassette| (cassette, &*cassette.mode)) {
+        Some((_, Mode::Replay(player))) => return Ok(Box::new(player.connect(url)?)),
+        Some((cassette, Mode::Record(recorder))) => Some((cassette, recorder)),
+        None => None,
+    };
+
+    let res = connect_async(url).await;
+    let Some((cassette, recorder)) = recorder else {
+        let (ws_stream, _) = res.map_err(|e| RpcError::Handshake(Box::new(e)))?;
+        return Ok(Box::new(ws_stream));
+    };
+
+    let conn: u64 = recorder.next_conn.fetch_add(1, Ordering::Relaxed);
+    let error: Option<String> = res.as_ref().err().map(ToString::to_string);
+    recorder.write(CassetteEvent::WsConnect { conn, url: url.to_string(), error });
+    let (ws_stream, _) = res.map_err(|e| RpcError::Handshake(Box::new(e)))?;
+    Ok(Box::new(RecordingConnection { inner: ws_stream, cassette: cassette.clone(), conn, ended: false }))
+}
+
+#[allow(dead_code)]
+struct RecordingConnection {
+    inner: WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
+    cassette: Cassette,
+    conn: u64,
+    ended: bool,
+}
+
+#[allow(dead_code)]
+impl RecordingConnection {
+    fn write(&self, event: CassetteEvent) {
+        if let Mode::Record(recorder) = &*self.cassette.mode {
+            recorder.write(event);
+        }
+    }
+}
+
+impl Stream for RecordingConnection {
+    type Item = Result<Message, tungstenite::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item: Option<Self::Item> = match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => return Poll::Pending,
+        };
+        let conn: u64 = self.conn;
+        match &item {
+            Some(Ok(message)) => {
+                if let Some(frame) = WsFrame::from_message(message) {
+                    self.write(CassetteEvent::WsFrame { conn, frame });
+                }
+            },
+            Some(Err(e)) => self.write(CassetteEvent::WsError { conn, error: e.to_string() }),
+            None if !self.ended => {
+                self.ended = true;
+                self.write(CassetteEvent::WsEnd { conn });
+            },
+            None => {},
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Sink<Message> for RecordingConnection {
+    type Error = tungstenite::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.inner).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// Replays the recorded frames at their offsets from the connect. Outgoing frames are swallowed,
+/// after a Close from our side (or a recorded drop) the stream ends.
+#[allow(dead_code)]
+struct ReplayConnection {
+    items: VecDeque<(Duration, ReplayItem)>,
+    start: Instant,
+    sleep: Option<Pin<Box<Sleep>>>,
+    closed: bool,
+    waker: Option<Waker>,  // reader, woken up when we close the connection
+}
+
+impl Stream for ReplayConnection {
+    type Item = Result<Message, tungstenite::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.closed {
+            return Poll::Ready(None);
+        }
+        self.waker = Some(cx.waker().clone());
+
+        // nothing left => the recording stopped while the connection was alive, it stays idle
+        let Some(deadline) = self.items.front().map(|(offset, _)| self.start + *offset) else { return Poll::Pending; };
+        if Instant::now() < deadline {
+            let sleep: &mut Pin<Box<Sleep>> = self.sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+            if sleep.deadline() != deadline {
+                sleep.as_mut().reset(deadline);
+            }
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        match self.items.pop_front().map(|(_, item)| item) {
+            Some(ReplayItem::Frame(message)) => Poll::Ready(Some(Ok(message))),
+            Some(ReplayItem::Error(error)) => {
+                self.closed = true;
+                let e: std::io::Error = std::io::Error::new(std::io::ErrorKind::ConnectionReset, error);
+                Poll::Ready(Some(Err(tungstenite::Error::Io(e))))
+            },
+            Some(ReplayItem::End) | None => {
+                self.closed = true;
+                Poll::Ready(None)
+            },
+        }
+    }
+}
+
+impl Sink<Message> for ReplayConnection {
+    type Error = tungstenite::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if let Message::Close(_) = item {
+            self.closed = true;
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
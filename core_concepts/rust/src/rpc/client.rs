@@ -0,0 +1,472 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+use reqwest::{
+    Client,
+    Response,
+    header::{HeaderMap, HeaderValue}
+};
+use serde::de::DeserializeOwned;
+
+use super::{
+    batch::Batch,
+    cache::ResponseCache,
+    coalesce::{Coalescer, NEVER_COALESCED},
+    cassette::Cassette,
+    communication::CommitmentLevel,
+    config::{
+        AccountInfoConfig,
+        ContextConfig,
+        SendTransactionConfig,
+        SignaturesForAddressConfig,
+        SimulateTransactionConfig,
+        TransactionConfig,
+        TxCommitment,
+    },
+    encoding::UiEncoding,
+    error::RpcError,
+    retry::{
+        RetryPolicy,
+        NoRetry,
+        RateLimiter,
+    },
+    types::{
+        RpcResponse,
+        RpcContextResponse,
+        EncodedTransactionWithMeta,
+        UiAccount,
+        Account,
+        LatestBlockhash,
+        SignatureInfo,
+        SignatureStatus,
+        PrioritizationFee,
+        SimulateTransactionResult,
+    },
+};
+
+/// ### Reusable HTTP JSON-RPC client
+/// Unlike `communication::get_transaction`, the underlying `reqwest::Client` is built ONCE,
+/// so keep-alive connections (and TLS sessions) are reused between the calls.
+///
+/// Cloning is cheap: clones share the same connection pool and the same `id` counter,
+/// so the client can be freely moved into spawned tasks.
+#[derive(Clone)]
+pub struct RpcClient {
+    url: reqwest::Url,
+    client: Client,
+    commitment: CommitmentLevel,
+    next_id: Arc<AtomicU64>,
+    retry: Arc<dyn RetryPolicy>,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Cassette>,
+    cache: Option<ResponseCache>,
+    coalescer: Option<Arc<Coalescer>>,
+}
+
+#[allow(dead_code)]
+impl RpcClient {
+    pub fn new<U: reqwest::IntoUrl>(url: U) -> Result<Self, RpcError> {
+        Self::new_with_commitment(url, CommitmentLevel::Confirmed)
+    }
+
+    pub fn new_with_commitment<U: reqwest::IntoUrl>(url: U, commitment: CommitmentLevel) -> Result<Self, RpcError> {
+        let mut headers: HeaderMap = HeaderMap::with_capacity(1);
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+
+        let client: Client = Client::builder()
+            .default_headers(headers)
+            .pool_idle_timeout(std::time::Duration::from_secs(90))
+            .tcp_keepalive(std::time::Duration::from_secs(60))
+            .build()?;
+
+        Ok(Self {
+            url: url.into_url()?,
+            client,
+            commitment,
+            next_id: Arc::new(AtomicU64::new(1)),
+            retry: Arc::new(NoRetry),
+            limiter: None,
+            cassette: None,
+            cache: None,
+            coalescer: None,
+        })
+    }
+
+    /// Failed requests are repeated according to the policy, e.g. `ExponentialBackoff::default()`.
+    pub fn with_retry_policy<R: RetryPolicy + 'static>(mut self, policy: R) -> Self {
+        self.retry = Arc::new(policy);
+        self
+    }
+
+    /// Every attempt (retries included) takes a token, see `RateLimiter` (panics on a non-positive rate).
+    /// Clones made AFTER this call share the same bucket.
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.limiter = Some(Arc::new(RateLimiter::new(requests_per_second, burst)));
+        self
+    }
+
+    /// Records every round trip into the cassette or, in replay mode, answers from it without any network.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Finalized transactions / blocks are answered from the cache after the first fetch, see `ResponseCache`.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Concurrent identical calls (same method and params, the commitment included) share one request, see `Coalescer`.
+    /// Clones made AFTER this call coalesce with each other. Batches and `NEVER_COALESCED` methods are always sent.
+    /// Failures of coalesced methods always come as `RpcError::Coalesced`, whether anyone joined the request or not.
+    pub fn with_coalescing(mut self) -> Self {
+        self.coalescer = Some(Arc::new(Coalescer::default()));
+        self
+    }
+
+    /// Calls, that were answered by an identical in-flight request instead of sending their own.
+    pub fn coalesced_requests(&self) -> u64 {
+        self.coalescer.as_ref().map_or(0, |coalescer| coalescer.coalesced())
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub fn commitment(&self) -> CommitmentLevel {
+        self.commitment
+    }
+
+    /// Every request gets a unique id, so responses can be matched back (see batches).
+    pub(crate) fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// ### Raw HTTP round trip
+    /// The only place, where the request actually leaves the process. Body is either a single JSON-RPC object or a batch (array).
+    pub(crate) async fn post(&self, request_json_rpc: &serde_json::Value) -> Result<String, RpcError> {
+        match &self.cassette {
+            Some(cassette) => cassette.http(request_json_rpc, self.post_to_network(request_json_rpc)).await,
+            None => self.post_to_network(request_json_rpc).await,
+        }
+    }
+
+    async fn post_to_network(&self, request_json_rpc: &serde_json::Value) -> Result<String, RpcError> {
+        let res: Response = self.client.post(self.url.clone()).json(request_json_rpc).send().await?;
+        Ok(check_status(res).await?.text().await?)
+    }
+
+    /// ### Runs `op` under the rate limiter and the retry policy
+    /// Shared by single calls and batches, so both are throttled and retried the same way.
+    pub(crate) async fn with_retry<T, F, Fut>(&self, mut op: F) -> Result<T, RpcError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, RpcError>>
+    {
+        let mut attempt: u32 = 0;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+
+            let e: RpcError = match op().await {
+                Ok(v) => return Ok(v),
+                Err(e) => e,
+            };
+
+            attempt += 1;
+            match self.retry.next_delay(attempt, &e) {
+                Some(delay) => {
+                    log::warn!("Request failed: {e}. Retrying in {delay:?} (attempt {attempt}).");
+                    tokio::time::sleep(delay).await;
+                },
+                None => return Err(e),
+            }
+        }
+    }
+
+    /// ### Single JSON-RPC round trip
+    /// `null` result is returned as `Ok(None)`, JSON-RPC `error` object as `Err(RpcError::JsonRpc)`.
+    pub async fn send<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<Option<T>, RpcError> {
+        let cached = self.cache.as_ref().and_then(|cache| Some((cache, ResponseCache::key(method, &params)?)));
+        let coalescer: Option<&Coalescer> = self.coalescer.as_deref().filter(|_| !NEVER_COALESCED.contains(&method));
+        if cached.is_none() && coalescer.is_none() {
+            return self.with_retry(|| self.send_once(method, &params)).await;
+        }
+
+        if let Some((cache, key)) = &cached {
+            if let Some(value) = cache.get(key) {
+                return Ok(Some(T::deserialize(&*value)?));
+            }
+        }
+        let value: Option<Arc<serde_json::Value>> = match coalescer {
+            Some(coalescer) => coalescer.run(method, &params, || self.with_retry(|| self.send_once(method, &params))).await?,
+            None => self.with_retry(|| self.send_once(method, &params)).await?.map(Arc::new),
+        };
+        let Some(value) = value else { return Ok(None); };
+        // deserialized first, so a response of the wrong shape doesn't get stuck in the cache
+        let res: T = T::deserialize(&*value)?;
+        if let Some((cache, key)) = cached {
+            cache.put(key, Arc::unwrap_or_clone(value));
+        }
+        Ok(Some(res))
+    }
+
+    async fn send_once<T: DeserializeOwned>(&self, method: &str, params: &serde_json::Value) -> Result<Option<T>, RpcError> {
+        let request_json_rpc: serde_json::Value = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id(),
+            "method": method,
+            "params": params
+        });
+
+        let res_body: String = self.post(&request_json_rpc).await?;
+
+        let res_json_rpc: RpcResponse<T> = serde_json::from_str(&res_body)?;
+        if let Some(e) = res_json_rpc.error {
+            return Err(e.into());
+        }
+
+        Ok(res_json_rpc.result)
+    }
+
+    /// Same as `send`, but `null` result is treated as an error (for methods, that always return something).
+    async fn send_expect<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T, RpcError> {
+        self.send::<T>(method, params)
+            .await?
+            .ok_or_else(|| RpcError::EmptyResult(method.to_string()))
+    }
+
+    /// Returns `Ok(None)` if the account doesn't exist. Data is requested as base64, see `get_account` for raw bytes.
+    pub async fn get_account_info(&self, pubkey: &str) -> Result<Option<UiAccount>, RpcError> {
+        self.get_account_info_with_config(pubkey, AccountInfoConfig::default()).await
+    }
+
+    pub async fn get_account_info_with_config(&self, pubkey: &str, config: AccountInfoConfig) -> Result<Option<UiAccount>, RpcError> {
+        Ok(self.get_account_info_with_context(pubkey, config).await?.value)
+    }
+
+    /// Same as `get_account_info_with_config`, but keeps the slot the state was read at.
+    pub async fn get_account_info_with_context(
+        &self,
+        pubkey: &str,
+        config: AccountInfoConfig
+    ) -> Result<RpcContextResponse<Option<UiAccount>>, RpcError> {
+        self.send_expect("getAccountInfo", self.get_account_info_params(pubkey, config)).await
+    }
+
+    /// ### getAccountInfo with raw data bytes
+    /// Works with any binary encoding (zstd is decompressed), jsonParsed is decoded only if the node fell back to base64.
+    pub async fn get_account(&self, pubkey: &str) -> Result<Option<Account>, RpcError> {
+        self.get_account_with_config(pubkey, AccountInfoConfig::default()).await
+    }
+
+    pub async fn get_account_with_config(&self, pubkey: &str, config: AccountInfoConfig) -> Result<Option<Account>, RpcError> {
+        self.get_account_info_with_config(pubkey, config)
+            .await?
+            .map(|account| account.decode())
+            .transpose()
+    }
+
+    /// Balance in lamports.
+    pub async fn get_balance(&self, pubkey: &str) -> Result<u64, RpcError> {
+        self.get_balance_with_config(pubkey, ContextConfig::default()).await
+    }
+
+    pub async fn get_balance_with_config(&self, pubkey: &str, config: ContextConfig) -> Result<u64, RpcError> {
+        let res: RpcContextResponse<u64> = self.send_expect("getBalance", self.get_balance_params(pubkey, config)).await?;
+        Ok(res.value)
+    }
+
+    /// `Ok(())` if the node is healthy, otherwise JSON-RPC error -32005 (NODE_UNHEALTHY) with the number of slots it's behind.
+    pub async fn get_health(&self) -> Result<(), RpcError> {
+        let status: String = self.send_expect("getHealth", serde_json::json!([])).await?;
+        match status.as_str() {
+            "ok" => Ok(()),
+            _ => Err(RpcError::InvalidRequest(format!("Unexpected getHealth status: {status}"))),
+        }
+    }
+
+    pub async fn get_slot(&self) -> Result<u64, RpcError> {
+        self.get_slot_with_config(ContextConfig::default()).await
+    }
+
+    pub async fn get_slot_with_config(&self, config: ContextConfig) -> Result<u64, RpcError> {
+        self.send_expect("getSlot", self.context_params(config)).await
+    }
+
+    /// Current block height, compare it with `LatestBlockhash::last_valid_block_height` to tell whether a tx has expired.
+    pub async fn get_block_height(&self) -> Result<u64, RpcError> {
+        self.get_block_height_with_config(ContextConfig::default()).await
+    }
+
+    pub async fn get_block_height_with_config(&self, config: ContextConfig) -> Result<u64, RpcError> {
+        self.send_expect("getBlockHeight", self.context_params(config)).await
+    }
+
+    pub async fn get_latest_blockhash(&self) -> Result<LatestBlockhash, RpcError> {
+        self.get_latest_blockhash_with_config(ContextConfig::default()).await
+    }
+
+    pub async fn get_latest_blockhash_with_config(&self, config: ContextConfig) -> Result<LatestBlockhash, RpcError> {
+        let res: RpcContextResponse<LatestBlockhash> = self.send_expect("getLatestBlockhash", self.context_params(config)).await?;
+        Ok(res.value)
+    }
+
+    pub async fn get_signatures_for_address(&self, address: &str, config: SignaturesForAddressConfig) -> Result<Vec<SignatureInfo>, RpcError> {
+        self.send_expect("getSignaturesForAddress", self.get_signatures_for_address_params(address, config)).await
+    }
+
+    /// Statuses of recent transactions (the node's status cache, ~150 blocks), `None` => unknown signature.
+    /// Up to 256 signatures per call.
+    pub async fn get_signature_statuses(&self, signatures: &[&str]) -> Result<Vec<Option<SignatureStatus>>, RpcError> {
+        let res: RpcContextResponse<Vec<Option<SignatureStatus>>> = self.send_expect("getSignatureStatuses", serde_json::json!([signatures])).await?;
+        Ok(res.value)
+    }
+
+    /// ### Submits a signed, base64-encoded transaction
+    /// Returns its signature, which means only that the node accepted it, see `TransactionSender` for the confirmation.
+    pub async fn send_transaction(&self, transaction_base64: &str) -> Result<String, RpcError> {
+        self.send_transaction_with_config(transaction_base64, SendTransactionConfig::default()).await
+    }
+
+    pub async fn send_transaction_with_config(&self, transaction_base64: &str, config: SendTransactionConfig) -> Result<String, RpcError> {
+        self.send_expect("sendTransaction", self.send_transaction_params(transaction_base64, config)).await
+    }
+
+    /// ### Executes a base64-encoded transaction without submitting it
+    /// The result is returned even if the transaction fails, check `err`. By default neither signatures
+    /// nor the blockhash are checked, see `SimulateTransactionConfig`.
+    pub async fn simulate_transaction(&self, transaction_base64: &str) -> Result<SimulateTransactionResult, RpcError> {
+        self.simulate_transaction_with_config(transaction_base64, SimulateTransactionConfig::default()).await
+    }
+
+    pub async fn simulate_transaction_with_config(
+        &self,
+        transaction_base64: &str,
+        config: SimulateTransactionConfig
+    ) -> Result<SimulateTransactionResult, RpcError> {
+        let res: RpcContextResponse<SimulateTransactionResult> =
+            self.send_expect("simulateTransaction", self.simulate_transaction_params(transaction_base64, config)).await?;
+        Ok(res.value)
+    }
+
+    /// Prioritization fees of the last 150 slots (the node's cache), up to 128 accounts.
+    /// No accounts => the lowest fee of the whole slot, which is 0 most of the time.
+    pub async fn get_recent_prioritization_fees(&self, accounts: &[&str]) -> Result<Vec<PrioritizationFee>, RpcError> {
+        self.send_expect("getRecentPrioritizationFees", serde_json::json!([accounts])).await
+    }
+
+    /// Returns `Ok(None)` if the transaction is not found. `transaction.decode()` gives the same result for every encoding.
+    pub async fn get_transaction(&self, signature: &str) -> Result<Option<EncodedTransactionWithMeta>, RpcError> {
+        self.get_transaction_with_config(signature, TransactionConfig::default()).await
+    }
+
+    pub async fn get_transaction_with_config(&self, signature: &str, config: TransactionConfig) -> Result<Option<EncodedTransactionWithMeta>, RpcError> {
+        self.send("getTransaction", self.get_transaction_params(signature, config)).await
+    }
+
+    // params builders are shared between single calls and batches (see `batch.rs`),
+    // `commitment: None` is filled with the client's default here
+
+    pub(crate) fn context_params(&self, mut config: ContextConfig) -> serde_json::Value {
+        config.commitment.get_or_insert(self.commitment);
+        serde_json::json!([config])
+    }
+
+    pub(crate) fn get_account_info_params(&self, pubkey: &str, mut config: AccountInfoConfig) -> serde_json::Value {
+        config.commitment.get_or_insert(self.commitment);
+        config.encoding.get_or_insert(UiEncoding::Base64);
+        serde_json::json!([pubkey, config])
+    }
+
+    pub(crate) fn get_balance_params(&self, pubkey: &str, mut config: ContextConfig) -> serde_json::Value {
+        config.commitment.get_or_insert(self.commitment);
+        serde_json::json!([pubkey, config])
+    }
+
+    pub(crate) fn get_signatures_for_address_params(&self, address: &str, mut config: SignaturesForAddressConfig) -> serde_json::Value {
+        config.commitment.get_or_insert(TxCommitment::at_least(self.commitment));
+        serde_json::json!([address, config])
+    }
+
+    pub(crate) fn get_transaction_params(&self, signature: &str, mut config: TransactionConfig) -> serde_json::Value {
+        config.encoding.get_or_insert(UiEncoding::Json);
+        config.commitment.get_or_insert(TxCommitment::at_least(self.commitment));
+        serde_json::json!([signature, config])
+    }
+
+    pub(crate) fn send_transaction_params(&self, transaction_base64: &str, mut config: SendTransactionConfig) -> serde_json::Value {
+        config.preflight_commitment.get_or_insert(self.commitment);
+        let mut options: serde_json::Value = serde_json::json!(config);
+        options["encoding"] = "base64".into();
+        serde_json::json!([transaction_base64, options])
+    }
+
+    pub(crate) fn simulate_transaction_params(&self, transaction_base64: &str, mut config: SimulateTransactionConfig) -> serde_json::Value {
+        config.commitment.get_or_insert(self.commitment);
+        let mut options: serde_json::Value = serde_json::json!(config);
+        options["encoding"] = "base64".into();
+        serde_json::json!([transaction_base64, options])
+    }
+
+    /// Starts an empty JSON-RPC batch, see `Batch`.
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+}
+
+/// ### Turns non-2xx responses into `RpcError::HttpStatus`
+/// Providers answer 429 (with `Retry-After`) when we exceed the quota, 5xx when the node is struggling.
+pub(crate) async fn check_status(res: Response) -> Result<Response, RpcError> {
+    let status: reqwest::StatusCode = res.status();
+    if status.is_success() { return Ok(res); }
+
+    let retry_after: Option<std::time::Duration> = res.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| {
+            let parsed: Option<std::time::Duration> = v.to_str().ok().and_then(|v| parse_retry_after(v, std::time::SystemTime::now()));
+            if parsed.is_none() {
+                log::warn!("Unsupported Retry-After {v:?}, falling back to the retry policy's backoff");
+            }
+            parsed
+        });
+    let body: String = res.text().await.unwrap_or_default();
+
+    Err(RpcError::HttpStatus { status: status.as_u16(), retry_after, body })
+}
+
+/// ### `Retry-After` value, relative to `now`
+/// Either delay-seconds (`120`) or an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`), the only date format
+/// servers are allowed to generate. A date in the past => retry right away.
+pub(crate) fn parse_retry_after(value: &str, now: std::time::SystemTime) -> Option<std::time::Duration> {
+    let value: &str = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(secs));
+    }
+
+    // "Sun," "06" "Nov" "1994" "08:49:37" "GMT"
+    let parts: Vec<&str> = value.split_ascii_whitespace().collect();
+    let [_weekday, day, month, year, time, "GMT"] = parts[..] else { return None; };
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let month: i64 = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+    let day: i64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let year: i64 = year.parse().ok()?;
+    let hms: Vec<u64> = time.split(':').map(str::parse).collect::<Result<_, _>>().ok()?;
+    let [h, m, s] = hms[..] else { return None; };
+    if h > 23 || m > 59 || s > 60 { return None; }
+
+    // days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's days_from_civil)
+    let y: i64 = if month <= 2 { year - 1 } else { year };
+    let era: i64 = y.div_euclid(400);
+    let yoe: i64 = y - era * 400;
+    let doy: i64 = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe: i64 = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days: i64 = era * 146_097 + doe - 719_468;
+
+    let at: u64 = u64::try_from(days).ok()? * 86_400 + h * 3_600 + m * 60 + s;
+    let at: std::time::SystemTime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(at);
+    Some(at.duration_since(now).unwrap_or_default())
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tokio::sync::watch;
+
+use super::error::RpcError;
+
+/// Methods, that are never coalesced: two identical calls are two intentional actions.
+pub const NEVER_COALESCED: [&str; 2] = ["sendTransaction", "requestAirdrop"];
+
+type Outcome = Result<Option<Arc<serde_json::Value>>, Arc<RpcError>>;
+type Inflight = Mutex<HashMap<String, watch::Receiver<Option<Outcome>>>>;
+
+/// ### Single-flight for identical requests
+/// The first caller of a (method, params) pair becomes the leader and sends the request, everyone who asks for
+/// the same pair until the answer arrives waits on the leader's `watch` channel instead of sending a duplicate.
+/// The client's params builders put the commitment into `params`, so different commitments are never mixed.
+///
+/// A failure is ALWAYS returned as `RpcError::Coalesced` (to the leader too, even if nobody joined), so the shape
+/// doesn't depend on timing. `is_retryable` / `code` look through it.
+/// If the leader is cancelled (its future dropped), one of the waiting callers takes over.
+#[derive(Default)]
+pub(crate) struct Coalescer {
+    inflight: Arc<Inflight>,
+    coalesced: AtomicU64,
+}
+
+enum Role {
+    Leader(watch::Sender<Option<Outcome>>),
+    Follower(watch::Receiver<Option<Outcome>>),
+}
+
+#[allow(dead_code)]
+impl Coalescer {
+    /// Requests, that were answered by someone else's flight.
+    pub(crate) fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    pub(crate) async fn run<F, Fut>(&self, method: &str, params: &serde_json::Value, request: F) -> Result<Option<Arc<serde_json::Value>>, RpcError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<serde_json::Value>, RpcError>>
+    {
+        let key: String = format!("{method}:{params}");
+        let tx: watch::Sender<Option<Outcome>> = loop {
+            match self.role(&key) {
+                Role::Leader(tx) => break tx,
+                Role::Follower(mut rx) => {
+                    if let Ok(outcome) = rx.wait_for(Option::is_some).await {
+                        self.coalesced.fetch_add(1, Ordering::Relaxed);
+                        return outcome.clone().unwrap().map_err(RpcError::Coalesced);
+                    }
+                    // the leader was dropped before answering
+                },
+            }
+        };
+
+        let guard: FlightGuard = FlightGuard { inflight: &self.inflight, key };
+        let res: Result<Option<serde_json::Value>, RpcError> = request().await;
+        // removed before publishing, so a caller arriving now sends a fresh request instead of reading a stale answer
+        drop(guard);
+
+        let outcome: Outcome = res.map(|value| value.map(Arc::new)).map_err(Arc::new);
+        if tx.receiver_count() > 0 {
+            tx.send_replace(Some(outcome.clone()));
+        }
+        outcome.map_err(RpcError::Coalesced)
+    }
+
+    fn role(&self, key: &str) -> Role {
+        let mut inflight = self.inflight.lock().unwrap();
+        match inflight.get(key) {
+            Some(rx) => Role::Follower(rx.clone()),
+            None => {
+                let (tx, rx) = watch::channel(None);
+                inflight.insert(key.to_string(), rx);
+                Role::Leader(tx)
+            },
+        }
+    }
+}
+
+/// Frees the key even if the leader's future is dropped mid-flight.
+struct FlightGuard<'a> {
+    inflight: &'a Inflight,
+    key: String,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        self.inflight.lock().unwrap().remove(&self.key);
+    }
+}
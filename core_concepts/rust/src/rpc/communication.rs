@@ -15,8 +15,33 @@ use tokio_tungstenite::{
     },
 };
 
+use tokio::sync::mpsc;
+
+use super::{
+    client::check_status,
+    config::{AccountInfoConfig, TransactionConfig, TxCommitment},
+    encoding::UiEncoding,
+    error::RpcError,
+    keepalive::{
+        Keepalive,
+        KeepaliveAction,
+        KeepaliveConfig,
+    },
+    subscription::{
+        Subscription,
+        Frame,
+        parse_frame,
+        SUBSCRIPTION_BUFFER,
+    },
+    types::{
+        RpcResponse,
+        EncodedTransactionWithMeta,
+        AccountNotification,
+    },
+};
+
 #[allow(dead_code)]
-#[derive(serde::Serialize, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]  // ordered: processed < confirmed < finalized
 #[serde(rename_all = "lowercase")]
 pub enum CommitmentLevel {
     Processed,
@@ -25,52 +50,68 @@ pub enum CommitmentLevel {
 }
 
 /// ### Simple Example of HTTP RPC request
-pub async fn get_transaction<U, S>(url: U, signature: S, commitment: CommitmentLevel) -> Result<(), Box<dyn std::error::Error>> 
+/// Returns `Ok(None)` if the transaction is not found (or not yet confirmed at the requested commitment),
+/// JSON-RPC `error` object is returned as `Err(RpcError::JsonRpc)`.
+/// Any `encoding` except jsonParsed, `transaction.decode()` turns it into the same `UiTransaction`.
+/// There is no client default here, so `commitment: None` => confirmed and `encoding: None` => json.
+pub async fn get_transaction<U, S>(url: U, signature: S, mut config: TransactionConfig) -> Result<Option<EncodedTransactionWithMeta>, RpcError> 
 where
     U: ToString + reqwest::IntoUrl,
     S: AsRef<[u8]> + serde::Serialize
 {
-    if commitment == CommitmentLevel::Processed { return Err("Commitment::Processed is not supported for getTransaction method!".into()); }
-
     // building http client
     let mut headers: HeaderMap = HeaderMap::with_capacity(1); 
     headers.insert("Content-Type", HeaderValue::from_static("application/json"));
     let client: Client = Client::builder().default_headers(headers).build()?;
 
+    config.commitment.get_or_insert(TxCommitment::Confirmed);
+    config.encoding.get_or_insert(UiEncoding::Json);
     let request_json_rpc: serde_json::Value = serde_json::json!({
         "jsonrpc": "2.0",
         "id": 1,
         "method": "getTransaction",
-        "params": [
-            signature,
-            {
-                "encoding": "json",
-                "commitment": commitment,
-                "maxSupportedTransactionVersion": 0
-            }
-        ]
+        "params": [signature, config]
     });
 
     let res: Response = client.post(url).json(&request_json_rpc).send().await?;
-    let res_body: String = res.text().await?;
+    let res_body: String = check_status(res).await?.text().await?;
 
-    // using serde_json::Value for simplicity, however it's recommended to use getTransaction scheme instead
-    if let Ok(tx) = serde_json::from_str::<serde_json::Value>(&res_body) {
-        log::info!("{:#?}", tx);
+    let res_json_rpc: RpcResponse<EncodedTransactionWithMeta> = serde_json::from_str(&res_body)?;
+    if let Some(e) = res_json_rpc.error {
+        return Err(e.into());
     }
 
-    Ok(())  // for simplicity sake returns nothing
+    Ok(res_json_rpc.result)  // `null` result => transaction not found
 }
 
 /// ### Simple WS RPC Stream Example without reconnection logic, however with proper stream cancelation
-pub async fn account_subscribe<U, P>(url: U, pubkey: P, commitment: CommitmentLevel) -> Result<(), Box<dyn std::error::Error>> 
+/// Handshake and subscription request happen before returning, then the socket is moved into a background task,
+/// which forwards typed notifications into the returned handle. Dropping the handle cancels the task.
+/// See `subscription::account_subscribe_supervised` for the version, that survives disconnects.
+/// `config` is the same as for getAccountInfo, see `AccountInfoConfig::for_subscription` for the defaults.
+pub async fn account_subscribe<U, P>(url: U, pubkey: P, config: AccountInfoConfig) -> Result<Subscription<AccountNotification>, RpcError> 
+where
+    U: ToString + tungstenite::client::IntoClientRequest + Unpin,
+    P: AsRef<[u8]> + serde::Serialize
+{
+    account_subscribe_with_keepalive(url, pubkey, config, KeepaliveConfig::default()).await
+}
+
+/// Same as `account_subscribe`, but with custom heartbeat settings.
+/// Dead connection (Pong / idle timeout) is surfaced as the last `Err` item of the stream.
+pub async fn account_subscribe_with_keepalive<U, P>(
+    url: U,
+    pubkey: P,
+    config: AccountInfoConfig,
+    keepalive: KeepaliveConfig
+) -> Result<Subscription<AccountNotification>, RpcError> 
 where
     U: ToString + tungstenite::client::IntoClientRequest + Unpin,
     P: AsRef<[u8]> + serde::Serialize
 {
     let (ws_stream, _) = connect_async(url)
         .await
-        .map_err(|_| "Failed to make a handshake!")?;
+        .map_err(|e| RpcError::Handshake(Box::new(e)))?;
 
     let (mut write, mut read) = ws_stream.split();
 
@@ -78,13 +119,7 @@ where
         "jsonrpc": "2.0",
         "id": 1,
         "method": "accountSubscribe",
-        "params": [
-            pubkey,
-            {
-                "encoding": "jsonParsed",
-                "commitment": commitment
-            }
-        ]
+        "params": [pubkey, config.for_subscription()]
     });
 
     if let Err(e) = write.send(Message::text(request_json_rpc.to_string())).await {
@@ -97,47 +132,81 @@ where
         return Err(e.into());
     }
 
-    loop {
-        tokio::select! {
-            Some(msg) = read.next() => {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        log::info!("{:#?}", text);
-                        // process text ... 
-                    },
-                    Ok(Message::Ping(v)) => {
-                        if let Err(_) = write.send(Message::Pong(v)).await {
-                            log::error!("Failed to send Pong Frame!");
-                            continue;
-                        }
-                        log::error!("Sent Pong Frame!");
-                    },
-                    Ok(Message::Close(frame)) => {
-                        log::error!("Received Close Frame! Closing stream.");
-                        try_to_close_connection(&mut write, frame).await;
-                    },
-                    Ok(_) => {},
-                    Err(e) => {
-                        match e {
-                            tungstenite::Error::ConnectionClosed => break log::info!("Connection is properly closed!"),
-                            _ => return Err(e.into())
-                        }
+    let (tx, rx) = mpsc::channel::<Result<AccountNotification, RpcError>>(SUBSCRIPTION_BUFFER);
+
+    tokio::task::spawn(async move {
+        let mut keepalive: Keepalive = Keepalive::new(keepalive);
+        loop {
+            tokio::select! {
+                msg = read.next() => {
+                    match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            match parse_frame::<AccountNotification>(&text, "accountNotification") {
+                                Frame::Notification(notification) => {
+                                    keepalive.on_notification();
+                                    if tx.send(notification).await.is_err() {
+                                        break try_to_close_connection(&mut write, None).await;
+                                    }
+                                },
+                                Frame::Rejected(e) => {
+                                    let _ = tx.send(Err(e.into())).await;
+                                    break try_to_close_connection(&mut write, None).await;
+                                },
+                                Frame::Subscribed(id) => log::info!("Subscribed! Subscription id: {id}"),
+                                Frame::Unknown => log::warn!("Unexpected message: {text}"),
+                            }
+                        },
+                        Some(Ok(Message::Ping(v))) => {
+                            if let Err(_) = write.send(Message::Pong(v)).await {
+                                log::error!("Failed to send Pong Frame!");
+                                continue;
+                            }
+                            log::error!("Sent Pong Frame!");
+                        },
+                        Some(Ok(Message::Pong(_))) => keepalive.on_pong(),
+                        Some(Ok(Message::Close(frame))) => {
+                            log::error!("Received Close Frame! Closing stream.");
+                            try_to_close_connection(&mut write, frame).await;
+                        },
+                        Some(Ok(_)) => {},
+                        Some(Err(e)) => {
+                            match e {
+                                tungstenite::Error::ConnectionClosed => break log::info!("Connection is properly closed!"),
+                                _ => {
+                                    let _ = tx.send(Err(e.into())).await;
+                                    break;
+                                }
+                            }
+                        },
+                        None => break log::info!("Stream ended!"),
                     }
-                }
-            },
+                },
+
+                // handle is dropped => nobody listens anymore
+                _ = tx.closed() => break try_to_close_connection(&mut write, None).await,
 
-            _ = tokio::time::sleep(tokio::time::Duration::from_millis(10000)) => {
-                if let Err(e) = write.send(Message::Ping(vec![].into())).await {
-                    log::error!("Failed to send a Heartbeat Ping! {e}");
+                action = keepalive.tick() => {
+                    match action {
+                        KeepaliveAction::Ping => {
+                            if let Err(e) = write.send(Message::Ping(vec![].into())).await {
+                                log::error!("Failed to send a Heartbeat Ping! {e}");
+                            }
+                        },
+                        KeepaliveAction::Dead(e) => {
+                            log::error!("{e}");
+                            let _ = tx.send(Err(e)).await;
+                            break try_to_close_connection(&mut write, None).await;
+                        }
+                    }
                 }
             }
         }
-    }
+    });
 
-    Ok(())
+    Ok(Subscription::new(rx))
 }
 
-async fn try_to_close_connection<T: SinkExt<Message> + Unpin>(write: &mut T, close_frame: Option<CloseFrame>) -> () {
+pub(crate) async fn try_to_close_connection<T: SinkExt<Message> + Unpin>(write: &mut T, close_frame: Option<CloseFrame>) -> () {
     if let Err(_) = write.send(Message::Close(close_frame)).await {
         log::error!("Failed to properly close connection!");
     }
@@ -0,0 +1,183 @@
+use serde::Serialize;
+
+use super::{
+    communication::CommitmentLevel,
+    encoding::UiEncoding,
+};
+
+/// ### Commitment of the methods, that reject `processed`
+/// getTransaction, getSignaturesForAddress, getBlock, ... answer `processed` with an error,
+/// having no such variant turns that error into a compile error.
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TxCommitment {
+    Confirmed,
+    Finalized,
+}
+
+impl TxCommitment {
+    /// Closest supported level to the client's default: `processed` is raised to `confirmed`.
+    pub fn at_least(commitment: CommitmentLevel) -> Self {
+        match commitment {
+            CommitmentLevel::Processed | CommitmentLevel::Confirmed => Self::Confirmed,
+            CommitmentLevel::Finalized => Self::Finalized,
+        }
+    }
+}
+
+impl From<TxCommitment> for CommitmentLevel {
+    fn from(commitment: TxCommitment) -> Self {
+        match commitment {
+            TxCommitment::Confirmed => Self::Confirmed,
+            TxCommitment::Finalized => Self::Finalized,
+        }
+    }
+}
+
+/// Returns only `length` bytes of account data starting from `offset` (binary encodings only).
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct DataSlice {
+    pub offset: usize,
+    pub length: usize,
+}
+
+// every config below is serialized as is into the last element of `params`, `None` fields are omitted.
+// `commitment: None` => the client's default commitment.
+
+/// getBalance, getSlot, getLatestBlockhash, ...
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+    /// Node answers -32016 (MIN_CONTEXT_SLOT_NOT_REACHED) until it has processed this slot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+}
+
+/// getAccountInfo
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfoConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<UiEncoding>,  // None => base64
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_slice: Option<DataSlice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+}
+
+impl AccountInfoConfig {
+    /// accountSubscribe / programSubscribe take the same config. Subscriptions have no client-side default,
+    /// so `commitment: None` is the node's default (finalized), spelled out to keep the catch-up fetches consistent.
+    pub(crate) fn for_subscription(mut self) -> Self {
+        self.commitment.get_or_insert(CommitmentLevel::Finalized);
+        self.encoding.get_or_insert(UiEncoding::Base64);
+        self
+    }
+}
+
+/// logsSubscribe, `commitment: None` => the node's default (finalized).
+#[allow(dead_code)]
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsSubscribeConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+}
+
+/// signatureSubscribe, `commitment: None` => the node's default (finalized).
+/// `enableReceivedNotification` is always off: the subscription is one-shot and yields the final result only.
+#[allow(dead_code)]
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureSubscribeConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+}
+
+/// getSignaturesForAddress, signatures are returned newest -> oldest.
+#[allow(dead_code)]
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SignaturesForAddressConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<TxCommitment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,  // start searching backwards from this signature (exclusive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,   // stop searching at this signature (exclusive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,    // 1..=1000, node's default is 1000
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+}
+
+/// getTransaction
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<TxCommitment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<UiEncoding>,  // None => json, jsonParsed doesn't fit `EncodedTransactionWithMeta`
+    /// `None` => only legacy transactions, a versioned one is answered with -32015 (UNSUPPORTED_TRANSACTION_VERSION)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_supported_transaction_version: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+}
+
+impl Default for TransactionConfig {
+    fn default() -> Self {
+        Self {
+            commitment: None,
+            encoding: None,
+            max_supported_transaction_version: Some(0),
+            min_context_slot: None,
+        }
+    }
+}
+
+/// sendTransaction, the transaction is always sent as base64.
+#[allow(dead_code)]
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SendTransactionConfig {
+    pub skip_preflight: bool,  // false => the node simulates the tx first and rejects it with -32002 if it fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preflight_commitment: Option<CommitmentLevel>,
+    /// How many times the node itself rebroadcasts the tx, `None` => until the blockhash expires.
+    /// Set `Some(0)`, when the rebroadcasting is done on our side (see `TransactionSender`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+}
+
+/// simulateTransaction, the transaction is always sent as base64.
+#[allow(dead_code)]
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateTransactionConfig {
+    pub sig_verify: bool,  // true => an unsigned tx fails with -32003
+    /// The node swaps in its latest blockhash, so unsigned txs with any blockhash can be simulated (conflicts with `sig_verify`)
+    pub replace_recent_blockhash: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+}
+
+impl Default for SimulateTransactionConfig {
+    fn default() -> Self {
+        Self {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: None,
+            min_context_slot: None,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::Read;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::RpcError,
+    types::{
+        UiTransaction,
+        UiMessage,
+        MessageHeader,
+        CompiledInstruction,
+        AddressTableLookup,
+    },
+};
+
+/// ### Wire format of account data / transactions
+/// - accounts: base58 (slow, the node rejects it for data over 128 bytes), base64, base64+zstd, jsonParsed
+/// - transactions: base58, base64, json, jsonParsed
+///
+/// The node answers an unsupported combination with -32602 (invalid params).
+/// jsonParsed falls back to base64 for accounts of programs the node has no parser for.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum UiEncoding {
+    #[serde(rename = "base58")]
+    Base58,
+    #[serde(rename = "base64")]
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
+
+/// ### Decodes `["<data>", "<encoding>"]` into raw bytes
+/// zstd frames are decompressed as well.
+#[allow(dead_code)]
+pub fn decode_binary(data: &str, encoding: UiEncoding) -> Result<Vec<u8>, RpcError> {
+    match encoding {
+        UiEncoding::Base58 => bs58::decode(data)
+            .into_vec()
+            .map_err(|e| RpcError::Decode(format!("base58: {e}"))),
+        UiEncoding::Base64 => BASE64
+            .decode(data)
+            .map_err(|e| RpcError::Decode(format!("base64: {e}"))),
+        UiEncoding::Base64Zstd => {
+            let compressed: Vec<u8> = BASE64
+                .decode(data)
+                .map_err(|e| RpcError::Decode(format!("base64: {e}")))?;
+            let mut bytes: Vec<u8> = Vec::new();
+            zstd::stream::read::Decoder::new(compressed.as_slice())
+                .and_then(|mut decoder| decoder.read_to_end(&mut bytes))
+                .map_err(|e| RpcError::Decode(format!("zstd: {e}")))?;
+            Ok(bytes)
+        },
+        UiEncoding::Json | UiEncoding::JsonParsed => Err(RpcError::Decode(format!("{encoding:?} is not a binary encoding"))),
+    }
+}
+
+/// ### Parses a serialized (wire format) transaction
+/// Legacy and v0 messages are supported, the result is the same as if the node was asked for `"encoding": "json"`
+/// (keys, blockhash and instruction data are base58 encoded).
+///
+/// Layout: `[signatures][message]`, message: `[version prefix (v0 only)][header][account keys][blockhash][instructions][lookups (v0 only)]`,
+/// every list is prefixed with its length as compact-u16 (shortvec).
+#[allow(dead_code)]
+pub fn decode_transaction(bytes: &[u8]) -> Result<UiTransaction, RpcError> {
+    let mut reader: WireReader = WireReader { bytes, pos: 0 };
+
+    let signatures: Vec<String> = reader.vec(|r| r.take(64).map(|sig| bs58::encode(sig).into_string()))?;
+
+    // the highest bit of the first byte is set only for versioned messages (legacy header can't have it,
+    // num_required_signatures would be >= 128)
+    let prefix: u8 = reader.u8()?;
+    let versioned: bool = prefix & 0x80 != 0;
+    let header: MessageHeader = match versioned {
+        true => {
+            if prefix & 0x7f != 0 {
+                return Err(RpcError::Decode(format!("Unsupported transaction version {}", prefix & 0x7f)));
+            }
+            MessageHeader { num_required_signatures: reader.u8()?, num_readonly_signed_accounts: reader.u8()?, num_readonly_unsigned_accounts: reader.u8()? }
+        },
+        false => MessageHeader { num_required_signatures: prefix, num_readonly_signed_accounts: reader.u8()?, num_readonly_unsigned_accounts: reader.u8()? },
+    };
+
+    let account_keys: Vec<String> = reader.vec(|r| r.pubkey())?;
+    let recent_blockhash: String = reader.pubkey()?;
+    let instructions: Vec<CompiledInstruction> = reader.vec(|r| {
+        Ok(CompiledInstruction {
+            program_id_index: r.u8()?,
+            accounts: r.vec(|r| r.u8())?,
+            data: bs58::encode(r.vec(|r| r.u8())?).into_string(),
+            stack_height: None,
+        })
+    })?;
+    let address_table_lookups: Option<Vec<AddressTableLookup>> = match versioned {
+        true => Some(reader.vec(|r| {
+            Ok(AddressTableLookup {
+                account_key: r.pubkey()?,
+                writable_indexes: r.vec(|r| r.u8())?,
+                readonly_indexes: r.vec(|r| r.u8())?,
+            })
+        })?),
+        false => None,
+    };
+
+    Ok(UiTransaction {
+        signatures,
+        message: UiMessage { header, account_keys, recent_blockhash, instructions, address_table_lookups },
+    })
+}
+
+#[allow(dead_code)]
+struct WireReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+#[allow(dead_code)]
+impl<'a> WireReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RpcError> {
+        let end: usize = self.pos + len;
+        let slice: &'a [u8] = self.bytes
+            .get(self.pos..end)
+            .ok_or_else(|| RpcError::Decode(format!("Transaction is truncated at byte {}", self.pos)))?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, RpcError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn pubkey(&mut self) -> Result<String, RpcError> {
+        Ok(bs58::encode(self.take(32)?).into_string())
+    }
+
+    /// compact-u16: 7 bits per byte, the highest bit means "more bytes follow", at most 3 bytes.
+    fn short_len(&mut self) -> Result<usize, RpcError> {
+        let mut len: usize = 0;
+        for i in 0..3 {
+            let byte: u8 = self.u8()?;
+            len |= usize::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(len);
+            }
+        }
+        Err(RpcError::Decode("compact-u16 is longer than 3 bytes".into()))
+    }
+
+    fn vec<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, RpcError>) -> Result<Vec<T>, RpcError> {
+        let len: usize = self.short_len()?;
+        (0..len).map(|_| item(self)).collect()
+    }
+}
@@ -0,0 +1,189 @@
+use std::{sync::Arc, time::Duration};
+use tokio_tungstenite::tungstenite::{
+    self,
+    protocol::CloseFrame,
+};
+
+use super::types::RpcResponseError;
+
+/// Server-side JSON-RPC error codes, that are worth matching on.
+/// Full list: https://github.com/anza-xyz/agave/blob/master/rpc-client-api/src/custom_error.rs
+#[allow(dead_code)]
+pub mod codes {
+    pub const BLOCK_CLEANED_UP: i64 = -32001;
+    pub const SEND_TRANSACTION_PREFLIGHT_FAILURE: i64 = -32002;
+    pub const TRANSACTION_SIGNATURE_VERIFICATION_FAILURE: i64 = -32003;
+    pub const BLOCK_NOT_AVAILABLE: i64 = -32004;
+    pub const NODE_UNHEALTHY: i64 = -32005;
+    pub const TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE: i64 = -32006;
+    pub const SLOT_SKIPPED: i64 = -32007;
+    pub const NO_SNAPSHOT: i64 = -32008;
+    pub const LONG_TERM_STORAGE_SLOT_SKIPPED: i64 = -32009;
+    pub const KEY_EXCLUDED_FROM_SECONDARY_INDEX: i64 = -32010;
+    pub const TRANSACTION_HISTORY_NOT_AVAILABLE: i64 = -32011;
+    pub const SCAN_ERROR: i64 = -32012;
+    pub const TRANSACTION_SIGNATURE_LEN_MISMATCH: i64 = -32013;
+    pub const BLOCK_STATUS_NOT_AVAILABLE_YET: i64 = -32014;
+    pub const UNSUPPORTED_TRANSACTION_VERSION: i64 = -32015;
+    pub const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+    pub const INTERNAL_ERROR: i64 = -32603;
+}
+
+/// ### Every error, that the rpc module can produce
+/// Each variant keeps the original source, so nothing is lost compared to `Box<dyn Error>`,
+/// but now callers can match on the failure kind (and decide whether to retry).
+#[allow(dead_code)]
+#[derive(thiserror::Error, Debug)]
+pub enum RpcError {
+    /// Connection refused / reset, DNS, timeouts, etc.
+    #[error("HTTP transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    /// Non-2xx HTTP status, e.g. 429 Too Many Requests. `retry_after` is parsed from the `Retry-After` header (seconds).
+    #[error("HTTP status {status}: {body}")]
+    HttpStatus {
+        status: u16,
+        retry_after: Option<Duration>,
+        body: String,
+    },
+
+    /// JSON-RPC `error` object returned by the node
+    #[error(transparent)]
+    JsonRpc(#[from] RpcResponseError),
+
+    /// Response (or notification) doesn't match the expected schema
+    #[error("Failed to deserialize: {0}")]
+    Deserialize(#[from] serde_json::Error),
+
+    /// Account data / transaction can't be decoded into raw bytes (bad base58 / base64 / zstd, truncated wire format, parsed JSON)
+    #[error("Failed to decode: {0}")]
+    Decode(String),
+
+    /// simulateTransaction ran, but the transaction itself failed (`err` is the node's TransactionError)
+    #[error("Simulation failed: {err}")]
+    SimulationFailed {
+        err: serde_json::Value,
+        logs: Vec<String>,
+    },
+
+    /// Method, that should always return a value, returned `null`
+    #[error("{0} returned null result!")]
+    EmptyResult(String),
+
+    /// Cassette file can't be read / written, or it has no recording for the request, see `Cassette`
+    #[error("Cassette: {0}")]
+    Cassette(String),
+
+    /// Disk store of the response cache can't be created, see `ResponseCache`
+    #[error("Cache: {0}")]
+    Cache(String),
+
+    /// Failure of a request sent by a coalescing client, see `RpcClient::with_coalescing`
+    #[error(transparent)]
+    Coalesced(Arc<RpcError>),
+
+    /// Batch response has no entry for the request with this `id`
+    #[error("No response for batch request id {0}")]
+    MissingBatchResponse(u64),
+
+    /// Request was rejected before sending, e.g. unsupported commitment for the method
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    // tungstenite::Error is large (it may carry the whole HTTP response), boxing keeps RpcError small
+    #[error("Failed to make a handshake: {0}")]
+    Handshake(#[source] Box<tungstenite::Error>),
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[source] Box<tungstenite::Error>),
+
+    /// Server sent a Close Frame (or the stream ended without it)
+    #[error("WebSocket closed: {0:?}")]
+    WsClosed(Option<CloseFrame>),
+
+    /// Heartbeat Ping wasn't answered in time, see `KeepaliveConfig`
+    #[error("No Pong within {0:?}, connection is dead")]
+    PongTimeout(Duration),
+
+    /// No notifications in time, see `KeepaliveConfig`
+    #[error("No notifications within {0:?}, connection is considered dead")]
+    IdleTimeout(Duration),
+
+    /// Subscriber didn't read its buffered notifications in time, the subscription was closed to not skip updates silently
+    #[error("Subscriber fell {0} notifications behind, subscription closed")]
+    Lagged(usize),
+
+    /// Failover moved the subscription away from this endpoint, see `FailoverClient`
+    #[error("Endpoint {0} is unhealthy, switching to another one")]
+    EndpointUnhealthy(String),
+}
+
+impl From<tungstenite::Error> for RpcError {
+    fn from(e: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(e))
+    }
+}
+
+#[allow(dead_code)]
+impl RpcError {
+    /// ### Whether the same request has a chance to succeed if repeated
+    /// Transient network failures, throttling, overloaded / lagging nodes => retryable.
+    /// Malformed requests, schema mismatches, skipped slots => not retryable, repeating won't help.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            Self::HttpStatus { status, .. } => *status == 408 || *status == 429 || *status >= 500,
+            Self::JsonRpc(e) => matches!(
+                e.code,
+                codes::BLOCK_NOT_AVAILABLE
+                    | codes::NODE_UNHEALTHY
+                    | codes::BLOCK_STATUS_NOT_AVAILABLE_YET
+                    | codes::MIN_CONTEXT_SLOT_NOT_REACHED
+                    | codes::INTERNAL_ERROR
+            ),
+            Self::Deserialize(_)
+                | Self::Decode(_)
+                | Self::SimulationFailed { .. }
+                | Self::Cassette(_)
+                | Self::Cache(_)
+                | Self::EmptyResult(_)
+                | Self::InvalidRequest(_) => false,
+            Self::Handshake(e) | Self::WebSocket(e) => matches!(
+                **e,
+                tungstenite::Error::ConnectionClosed
+                    | tungstenite::Error::AlreadyClosed
+                    | tungstenite::Error::Io(_)
+                    | tungstenite::Error::Tls(_)
+                    | tungstenite::Error::Protocol(_)
+            ) || matches!(&**e, tungstenite::Error::Http(res) if res.status().as_u16() == 429 || res.status().is_server_error()),
+            Self::Coalesced(e) => e.is_retryable(),
+            Self::WsClosed(_)
+                | Self::PongTimeout(_)
+                | Self::IdleTimeout(_)
+                | Self::MissingBatchResponse(_)
+                | Self::Lagged(_)
+                | Self::EndpointUnhealthy(_) => true,
+        }
+    }
+
+    /// ### Copy of the error for every subscriber of a dead shared connection
+    /// Sources (reqwest / tungstenite errors) aren't Clone, so these are flattened to `WsClosed(None)`,
+    /// but the variant (and so `is_retryable`) is preserved for the connection-level ones.
+    pub(crate) fn fan_out(&self) -> Self {
+        match self {
+            Self::WsClosed(frame) => Self::WsClosed(frame.clone()),
+            Self::PongTimeout(d) => Self::PongTimeout(*d),
+            Self::IdleTimeout(d) => Self::IdleTimeout(*d),
+            _ => Self::WsClosed(None),
+        }
+    }
+
+    /// JSON-RPC error code, if the node returned one.
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            Self::JsonRpc(e) => Some(e.code),
+            Self::Coalesced(e) => e.code(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        Weak,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use rand::Rng;
+use tokio::sync::{mpsc, watch};
+
+use super::{
+    client::RpcClient,
+    communication::CommitmentLevel,
+    config::AccountInfoConfig,
+    error::RpcError,
+    keepalive::KeepaliveConfig,
+    subscription::{self, ConnectionEvent, ReconnectPolicy, Subscription, SubscriptionTarget},
+    types::AccountNotification,
+};
+
+/// ### Single RPC provider
+/// `weight` is the share of HTTP requests it gets, while healthy (relative to the other healthy ones).
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub http_url: String,
+    pub ws_url: String,
+    pub weight: u32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct FailoverConfig {
+    pub commitment: CommitmentLevel,
+    pub health_check_interval: Duration,
+    pub max_slot_lag: u64,            // endpoint is unhealthy, if it's this many slots behind the best one
+    pub max_consecutive_errors: u32,  // endpoint is unhealthy (until the next passed health check) after this many failed requests in a row
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentLevel::Confirmed,
+            health_check_interval: Duration::from_secs(10),
+            max_slot_lag: 50,
+            max_consecutive_errors: 3,
+        }
+    }
+}
+
+/// ### RPC client over several providers
+/// - HTTP requests go to a healthy endpoint picked at random by weight, a retryable failure is retried on another one.
+/// - Health checks (`getHealth` + `getSlot` lag against the others) run every `health_check_interval`, starting right in `new()`,
+///   the task stops together with the last clone of the client.
+/// - Supervised WebSocket subscriptions stay on their endpoint until it turns unhealthy, then move to the preferred one.
+///   Failed connects count as errors of the endpoint, same as failed HTTP requests.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct FailoverClient {
+    inner: Arc<Inner>,
+}
+
+#[allow(dead_code)]
+struct Inner {
+    endpoints: Vec<EndpointState>,
+    config: FailoverConfig,
+    // ws url (+ its http client for the catch-up) of the preferred endpoint, changes only when the current one turns unhealthy (sticky)
+    preferred_ws: watch::Sender<SubscriptionTarget>,
+}
+
+#[allow(dead_code)]
+struct EndpointState {
+    endpoint: Endpoint,
+    client: RpcClient,
+    healthy: AtomicBool,
+    slot: AtomicU64,
+    consecutive_errors: AtomicU32,
+}
+
+#[allow(dead_code)]
+impl FailoverClient {
+    /// Must be called within a Tokio runtime, the health checks are spawned right away.
+    pub fn new(endpoints: Vec<Endpoint>, config: FailoverConfig) -> Result<Self, RpcError> {
+        if endpoints.is_empty() {
+            return Err(RpcError::InvalidRequest("At least one endpoint is required!".into()));
+        }
+
+        let endpoints: Vec<EndpointState> = endpoints
+            .into_iter()
+            .map(|endpoint| Ok(EndpointState {
+                client: RpcClient::new_with_commitment(endpoint.http_url.as_str(), config.commitment)?,
+                endpoint,
+                healthy: AtomicBool::new(true),  // optimistic until the first health check
+                slot: AtomicU64::new(0),
+                consecutive_errors: AtomicU32::new(0),
+            }))
+            .collect::<Result<_, RpcError>>()?;
+
+        let inner: Arc<Inner> = Arc::new_cyclic(|weak: &Weak<Inner>| {
+            let (i, state) = endpoints
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, state)| state.endpoint.weight)
+                .expect("endpoints aren't empty");
+            let preferred_ws: SubscriptionTarget = subscription_target(weak, i, state);
+            Inner { endpoints, config, preferred_ws: watch::Sender::new(preferred_ws) }
+        });
+
+        let client: Self = Self { inner };
+        client.spawn_health_checks();
+        Ok(client)
+    }
+
+    /// Periodic health checks, the task exits once every clone of the client is dropped.
+    fn spawn_health_checks(&self) {
+        let weak: Weak<Inner> = Arc::downgrade(&self.inner);
+        let interval: Duration = self.inner.config.health_check_interval;
+
+        tokio::task::spawn(async move {
+            loop {
+                let Some(inner) = weak.upgrade() else { return; };
+                FailoverClient { inner }.health_check().await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    /// ### Checks every endpoint concurrently
+    /// Unhealthy == `getHealth` failed, `getSlot` failed or the slot lags more than `max_slot_lag` behind the best endpoint.
+    pub async fn health_check(&self) {
+        let checks = self.inner.endpoints.iter().map(|state| async move {
+            let health: Result<(), RpcError> = state.client.get_health().await;
+            let slot: Result<u64, RpcError> = state.client.get_slot().await;
+            match (health, slot) {
+                (Ok(()), Ok(slot)) => Some(slot),
+                (Err(e), _) | (_, Err(e)) => {
+                    log::warn!("Endpoint {} failed the health check: {e}", state.endpoint.http_url);
+                    None
+                }
+            }
+        });
+        let slots: Vec<Option<u64>> = futures_util::future::join_all(checks).await;
+
+        let best_slot: u64 = slots.iter().flatten().copied().max().unwrap_or(0);
+        for (state, slot) in self.inner.endpoints.iter().zip(slots) {
+            let healthy: bool = slot.is_some_and(|slot| best_slot.saturating_sub(slot) <= self.inner.config.max_slot_lag);
+            if let Some(slot) = slot {
+                state.slot.store(slot, Ordering::Relaxed);
+            }
+            if healthy {
+                state.consecutive_errors.store(0, Ordering::Relaxed);
+            } else if state.healthy.load(Ordering::Relaxed) {
+                log::warn!("Endpoint {} is unhealthy (slot {slot:?}, best {best_slot})", state.endpoint.http_url);
+            }
+            state.healthy.store(healthy, Ordering::Relaxed);
+        }
+
+        self.update_preferred_ws();
+    }
+
+    pub fn healthy_endpoints(&self) -> Vec<&Endpoint> {
+        self.inner.endpoints
+            .iter()
+            .filter(|state| state.healthy.load(Ordering::Relaxed))
+            .map(|state| &state.endpoint)
+            .collect()
+    }
+
+    /// ### Runs `op` against the endpoints until one succeeds
+    /// Retryable errors count against the endpoint and move the request to another one,
+    /// non-retryable errors are returned right away (they would fail everywhere).
+    ///
+    /// ```ignore
+    /// let slot: u64 = failover.call(|client| async move { client.get_slot().await }).await?;
+    /// ```
+    pub async fn call<T, F, Fut>(&self, op: F) -> Result<T, RpcError>
+    where
+        F: Fn(RpcClient) -> Fut,
+        Fut: Future<Output = Result<T, RpcError>>
+    {
+        let mut tried: Vec<usize> = Vec::with_capacity(self.inner.endpoints.len());
+        loop {
+            let i: usize = self.pick(&tried);
+            let state: &EndpointState = &self.inner.endpoints[i];
+
+            match op(state.client.clone()).await {
+                Ok(v) => {
+                    state.consecutive_errors.store(0, Ordering::Relaxed);
+                    return Ok(v);
+                },
+                Err(e) if e.is_retryable() => {
+                    self.on_error(i, &e);
+                    tried.push(i);
+                    if tried.len() >= self.inner.endpoints.len() {
+                        return Err(e);
+                    }
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// ### Supervised accountSubscribe, that follows the preferred endpoint
+    /// See `subscription::account_subscribe_supervised`, the only difference is where the reconnects go.
+    /// The catch-up after a reconnect is always on, it's done over the http url of the new endpoint.
+    pub fn account_subscribe(
+        &self,
+        pubkey: &str,
+        config: AccountInfoConfig,
+        policy: ReconnectPolicy,
+        keepalive: KeepaliveConfig,
+        events: mpsc::UnboundedSender<ConnectionEvent>
+    ) -> Subscription<AccountNotification> {
+        subscription::account_subscribe_supervised_on(self.inner.preferred_ws.subscribe(), pubkey, config, policy, keepalive, events)
+    }
+
+    /// Weighted random among healthy endpoints, that weren't tried yet.
+    /// If none of them is healthy, the untried ones are used anyway (better than failing right away).
+    fn pick(&self, tried: &[usize]) -> usize {
+        let untried = || (0..self.inner.endpoints.len()).filter(|i| !tried.contains(i));
+        let mut candidates: Vec<usize> = untried()
+            .filter(|&i| self.inner.endpoints[i].healthy.load(Ordering::Relaxed))
+            .collect();
+        if candidates.is_empty() {
+            candidates = untried().collect();
+        }
+
+        let total: u64 = candidates.iter().map(|&i| u64::from(self.inner.endpoints[i].endpoint.weight.max(1))).sum();
+        let mut roll: u64 = rand::rng().random_range(0..total);
+        for &i in &candidates {
+            let weight: u64 = u64::from(self.inner.endpoints[i].endpoint.weight.max(1));
+            if roll < weight {
+                return i;
+            }
+            roll -= weight;
+        }
+        candidates[0]
+    }
+
+    fn on_error(&self, i: usize, e: &RpcError) {
+        let state: &EndpointState = &self.inner.endpoints[i];
+        let errors: u32 = state.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        log::warn!("Endpoint {} failed ({errors} in a row): {e}", state.endpoint.http_url);
+
+        if errors >= self.inner.config.max_consecutive_errors && state.healthy.swap(false, Ordering::Relaxed) {
+            log::warn!("Endpoint {} is unhealthy after {errors} errors in a row", state.endpoint.http_url);
+            self.update_preferred_ws();
+        }
+    }
+
+    /// Keeps the current preferred ws endpoint while it's healthy, otherwise switches to the healthy one with the highest weight.
+    fn update_preferred_ws(&self) {
+        let healthy = |url: &str| self.inner.endpoints
+            .iter()
+            .any(|state| state.endpoint.ws_url == url && state.healthy.load(Ordering::Relaxed));
+
+        if healthy(&self.inner.preferred_ws.borrow().ws_url) {
+            return;
+        }
+
+        let next: Option<SubscriptionTarget> = self.inner.endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| state.healthy.load(Ordering::Relaxed))
+            .max_by_key(|(_, state)| state.endpoint.weight)
+            .map(|(i, state)| subscription_target(&Arc::downgrade(&self.inner), i, state));
+
+        if let Some(next) = next {
+            log::warn!("Moving subscriptions to {}", next.ws_url);
+            self.inner.preferred_ws.send_replace(next);
+        }
+    }
+}
+
+/// Endpoint `i` as a subscription target, its failed connects are counted by `on_error`.
+#[allow(dead_code)]
+fn subscription_target(inner: &Weak<Inner>, i: usize, state: &EndpointState) -> SubscriptionTarget {
+    let inner: Weak<Inner> = inner.clone();
+    SubscriptionTarget::new(&state.endpoint.ws_url)
+        .with_resync(state.client.clone())
+        .with_connect_error_hook(move |e| {
+            if let Some(inner) = inner.upgrade() {
+                FailoverClient { inner }.on_error(i, e);
+            }
+        })
+}
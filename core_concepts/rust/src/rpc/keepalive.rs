@@ -0,0 +1,85 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::error::RpcError;
+
+/// ### Heartbeat settings of a WebSocket connection
+/// - Ping is sent every `ping_interval`, no matter how busy the stream is
+///   (recreating a `sleep` on every loop iteration would never fire on a busy stream).
+/// - If the Pong doesn't arrive within `pong_timeout`, the connection is considered dead.
+/// - If no notification arrives within `idle_timeout`, the connection is considered dead as well.
+///   Disabled by default, because an account may legitimately stay unchanged for hours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeepaliveConfig {
+    pub ping_interval: Duration,
+    pub pong_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(10),
+            pong_timeout: Duration::from_secs(10),
+            idle_timeout: None,
+        }
+    }
+}
+
+pub enum KeepaliveAction {
+    Ping,
+    Dead(RpcError),
+}
+
+/// ### Deadlines of a single connection
+/// Meant to be used as a `tokio::select!` branch: `action = keepalive.tick() => { ... }`.
+/// `tick` is cancel safe, the state changes only after the sleep completes.
+pub struct Keepalive {
+    config: KeepaliveConfig,
+    next_ping: Instant,
+    pong_deadline: Option<Instant>,  // Some => waiting for the Pong
+    idle_deadline: Option<Instant>,
+}
+
+impl Keepalive {
+    pub fn new(config: KeepaliveConfig) -> Self {
+        let now: Instant = Instant::now();
+        Self {
+            config,
+            next_ping: now + config.ping_interval,
+            pong_deadline: None,
+            idle_deadline: config.idle_timeout.map(|timeout| now + timeout),
+        }
+    }
+
+    pub fn on_pong(&mut self) {
+        self.pong_deadline = None;
+    }
+
+    pub fn on_notification(&mut self) {
+        self.idle_deadline = self.config.idle_timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    pub async fn tick(&mut self) -> KeepaliveAction {
+        let deadline: Instant = [Some(self.next_ping), self.pong_deadline, self.idle_deadline]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(self.next_ping);
+        tokio::time::sleep_until(deadline).await;
+
+        let now: Instant = Instant::now();
+        if self.pong_deadline.is_some_and(|d| now >= d) {
+            return KeepaliveAction::Dead(RpcError::PongTimeout(self.config.pong_timeout));
+        }
+        if self.idle_deadline.is_some_and(|d| now >= d) {
+            // idle_deadline is Some only if idle_timeout is Some
+            return KeepaliveAction::Dead(RpcError::IdleTimeout(self.config.idle_timeout.unwrap_or_default()));
+        }
+
+        self.next_ping = now + self.config.ping_interval;
+        // the previous Ping may still be in flight, its deadline is kept
+        self.pong_deadline.get_or_insert(now + self.config.pong_timeout);
+        KeepaliveAction::Ping
+    }
+}
@@ -0,0 +1,188 @@
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+use super::{
+    error::RpcError,
+    types::{LogsNotification, TransactionMeta},
+};
+
+/// ### Call tree rebuilt from `logMessages`
+/// The runtime logs every (cross-program) invocation as `invoke [depth]` ... `success` / `failed: <err>`,
+/// everything in between belongs to the innermost open invocation.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParsedLogs {
+    pub invocations: Vec<ProgramInvocation>,  // top-level instructions, in order
+    pub truncated: bool,  // "Log truncated", the node keeps only the first 10KB of logs
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramInvocation {
+    pub program_id: String,
+    pub depth: u32,  // 1 == top-level instruction
+    pub instruction: Option<String>,  // Anchor's "Program log: Instruction: UpdatePda"
+    pub logs: Vec<String>,  // `msg!` lines without the "Program log: " prefix
+    pub data: Vec<Vec<u8>>,  // "Program data: <base64>..." (Anchor events), one item per base64 chunk
+    pub return_data: Option<Vec<u8>>,  // "Program return: <program_id> <base64>"
+    pub compute_units: Option<ComputeUnits>,
+    pub result: InvocationResult,
+    pub inner: Vec<ProgramInvocation>,  // CPIs made by this invocation
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeUnits {
+    pub consumed: u64,
+    pub limit: u64,  // what was left for this invocation when it started
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvocationResult {
+    Success,
+    Failed(String),  // e.g. "custom program error: 0x1771"
+    Incomplete,  // logs were truncated (or the transaction is still being logged) before the invocation finished
+}
+
+#[allow(dead_code)]
+impl ProgramInvocation {
+    fn new(program_id: &str, depth: u32) -> Self {
+        Self {
+            program_id: program_id.to_string(),
+            depth,
+            instruction: None,
+            logs: Vec::new(),
+            data: Vec::new(),
+            return_data: None,
+            compute_units: None,
+            result: InvocationResult::Incomplete,
+            inner: Vec::new(),
+        }
+    }
+
+    /// This invocation and all of its CPIs, depth-first (in log order).
+    pub fn iter(&self) -> impl Iterator<Item = &ProgramInvocation> {
+        let mut stack: Vec<&ProgramInvocation> = vec![self];
+        std::iter::from_fn(move || {
+            let next: &ProgramInvocation = stack.pop()?;
+            stack.extend(next.inner.iter().rev());
+            Some(next)
+        })
+    }
+}
+
+#[allow(dead_code)]
+impl ParsedLogs {
+    /// Every invocation of the program, no matter how deep.
+    pub fn invocations_of<'a>(&'a self, program_id: &'a str) -> impl Iterator<Item = &'a ProgramInvocation> {
+        self.invocations
+            .iter()
+            .flat_map(ProgramInvocation::iter)
+            .filter(move |invocation| invocation.program_id == program_id)
+    }
+
+    pub fn succeeded(&self) -> bool {
+        !self.invocations.is_empty() && self.invocations.iter().all(|invocation| invocation.result == InvocationResult::Success)
+    }
+}
+
+/// ### Parses `meta.logMessages` / `logsNotification.logs`
+/// Lines the parser doesn't know are skipped, `Err` only if the tree itself is broken
+/// (`success` of a program that isn't running) or event data is not base64.
+#[allow(dead_code)]
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Result<ParsedLogs, RpcError> {
+    let mut parsed: ParsedLogs = ParsedLogs::default();
+    let mut stack: Vec<ProgramInvocation> = Vec::new();
+
+    for line in logs.iter().map(AsRef::as_ref) {
+        if line == "Log truncated" {
+            parsed.truncated = true;
+            continue;
+        }
+        let Some(rest) = line.strip_prefix("Program ") else {
+            log::debug!("Skipping log line: {line}");
+            continue;
+        };
+
+        if let Some(msg) = rest.strip_prefix("log: ") {
+            let Some(current) = stack.last_mut() else { continue; };
+            match msg.strip_prefix("Instruction: ") {
+                Some(name) if current.instruction.is_none() => current.instruction = Some(name.to_string()),
+                _ => current.logs.push(msg.to_string()),
+            }
+        } else if let Some(data) = rest.strip_prefix("data: ") {
+            let Some(current) = stack.last_mut() else { continue; };
+            for chunk in data.split_whitespace() {
+                current.data.push(decode_base64(chunk)?);
+            }
+        } else if let Some(data) = rest.strip_prefix("return: ") {
+            let Some(current) = stack.last_mut() else { continue; };
+            // "<program_id> <base64>", empty return data has no base64 part
+            let data: &str = data.split_once(' ').map(|(_, data)| data).unwrap_or_default();
+            current.return_data = Some(decode_base64(data)?);
+        } else if let Some((program_id, event)) = rest.split_once(' ') {
+            if let Some(depth) = event.strip_prefix("invoke [").and_then(|depth| depth.strip_suffix(']')) {
+                let depth: u32 = depth.parse().map_err(|_| RpcError::Decode(format!("Bad invoke depth: {line}")))?;
+                stack.push(ProgramInvocation::new(program_id, depth));
+            } else if event == "success" {
+                close(&mut stack, &mut parsed, program_id, InvocationResult::Success)?;
+            } else if let Some(err) = event.strip_prefix("failed: ") {
+                close(&mut stack, &mut parsed, program_id, InvocationResult::Failed(err.to_string()))?;
+            } else if let Some(units) = event.strip_prefix("consumed ").and_then(|units| units.strip_suffix(" compute units")) {
+                let Some(current) = stack.last_mut().filter(|current| current.program_id == program_id) else { continue; };
+                // "<consumed> of <limit>"
+                let units: Option<(u64, u64)> = units
+                    .split_once(" of ")
+                    .and_then(|(consumed, limit)| Some((consumed.parse().ok()?, limit.parse().ok()?)));
+                current.compute_units = units.map(|(consumed, limit)| ComputeUnits { consumed, limit });
+            } else {
+                log::debug!("Skipping log line: {line}");
+            }
+        }
+    }
+
+    // whatever is still open was cut off
+    while let Some(invocation) = stack.pop() {
+        attach(&mut stack, &mut parsed, invocation);
+    }
+    Ok(parsed)
+}
+
+#[allow(dead_code)]
+fn close(stack: &mut Vec<ProgramInvocation>, parsed: &mut ParsedLogs, program_id: &str, result: InvocationResult) -> Result<(), RpcError> {
+    let mut invocation: ProgramInvocation = stack
+        .pop()
+        .filter(|invocation| invocation.program_id == program_id)
+        .ok_or_else(|| RpcError::Decode(format!("Program {program_id} finished, but it wasn't invoked")))?;
+    invocation.result = result;
+    attach(stack, parsed, invocation);
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn attach(stack: &mut [ProgramInvocation], parsed: &mut ParsedLogs, invocation: ProgramInvocation) {
+    match stack.last_mut() {
+        Some(parent) => parent.inner.push(invocation),
+        None => parsed.invocations.push(invocation),
+    }
+}
+
+#[allow(dead_code)]
+fn decode_base64(data: &str) -> Result<Vec<u8>, RpcError> {
+    BASE64.decode(data).map_err(|e| RpcError::Decode(format!("base64 in logs: {e}")))
+}
+
+#[allow(dead_code)]
+impl TransactionMeta {
+    /// `Ok(None)` if the node didn't return logs (e.g. `--enable-rpc-transaction-history` without log storage).
+    pub fn parse_logs(&self) -> Result<Option<ParsedLogs>, RpcError> {
+        self.log_messages.as_deref().map(parse_logs).transpose()
+    }
+}
+
+#[allow(dead_code)]
+impl LogsNotification {
+    pub fn parse_logs(&self) -> Result<ParsedLogs, RpcError> {
+        parse_logs(&self.logs)
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use futures_util::{
+    Stream,
+    StreamExt,
+    stream::{BoxStream, SelectAll},
+};
+use tokio::time::Instant;
+
+use super::{
+    error::RpcError,
+    types::AccountNotification,
+};
+
+/// How many emitted (slot, content) pairs are remembered to recognize late copies.
+#[allow(dead_code)]
+const DEDUP_WINDOW: usize = 64;
+
+/// ### Notification, that can be merged across redundant subscriptions
+/// Two copies are the same update if both the slot and the content hash match.
+#[allow(dead_code)]
+pub trait MergeableNotification {
+    fn slot(&self) -> u64;
+    fn content_hash(&self) -> u64;
+}
+
+impl MergeableNotification for AccountNotification {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    /// Hash of the decoded state, so providers may even use different binary encodings (base64 vs base64+zstd).
+    fn content_hash(&self) -> u64 {
+        let mut hasher: DefaultHasher = DefaultHasher::new();
+        self.account.lamports.hash(&mut hasher);
+        self.account.owner.hash(&mut hasher);
+        self.account.executable.hash(&mut hasher);
+        match self.account.data.decode() {
+            Ok(data) => data.hash(&mut hasher),
+            // jsonParsed (or undecodable) data is hashed as is
+            Err(_) => format!("{:?}", self.account.data).hash(&mut hasher),
+        }
+        hasher.finish()
+    }
+}
+
+/// Notification and the index of the source, that delivered it first.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merged<T> {
+    pub source: usize,
+    pub notification: T,
+}
+
+/// ### Per-source counters, for comparing provider latency
+/// - `first`: updates this source delivered before the others (= emitted from it).
+/// - `late`: copies of already emitted updates, `lag` is their total delay behind the first copy.
+/// - `stale`: updates older than the latest emitted slot, dropped.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceStats {
+    pub first: u64,
+    pub late: u64,
+    pub stale: u64,
+    pub lag: Duration,
+}
+
+#[allow(dead_code)]
+impl SourceStats {
+    /// Average delay behind the fastest source, `None` if it never delivered a copy late.
+    pub fn mean_lag(&self) -> Option<Duration> {
+        (self.late > 0).then(|| self.lag / self.late as u32)
+    }
+}
+
+#[allow(dead_code)]
+struct Emitted {
+    slot: u64,
+    hash: u64,
+    at: Instant,
+}
+
+/// ### Redundant subscriptions of the same key merged into one stream
+/// Items are polled from all sources as they arrive and emitted only if they are new:
+/// - a copy of an emitted update (same slot + content hash) is dropped and counted as `late` for its source,
+/// - an update older than the latest emitted slot is dropped as `stale`, the state never goes back in time,
+/// - a different content at the same slot is emitted (the account changed again within the slot).
+///
+/// Errors are passed through and the other sources go on, the stream ends once ALL sources have ended.
+///
+/// ```ignore
+/// let primary = pubsub_a.account_subscribe(pda, AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }).await?;
+/// let backup = pubsub_b.account_subscribe(pda, AccountInfoConfig { commitment: Some(CommitmentLevel::Confirmed), ..Default::default() }).await?;
+/// let mut merged = merge_notifications(vec![primary, backup]);
+/// while let Some(Ok(Merged { source, notification })) = merged.next().await { ... }
+/// log::info!("{:?}", merged.stats());
+/// ```
+#[allow(dead_code)]
+pub struct MergedNotifications<T> {
+    inner: SelectAll<BoxStream<'static, (usize, Result<T, RpcError>)>>,
+    emitted: VecDeque<Emitted>,
+    latest_slot: Option<u64>,
+    stats: Vec<SourceStats>,
+}
+
+#[allow(dead_code)]
+pub fn merge_notifications<T, S>(sources: Vec<S>) -> MergedNotifications<T>
+where
+    T: MergeableNotification + Send + 'static,
+    S: Stream<Item = Result<T, RpcError>> + Send + 'static
+{
+    let stats: Vec<SourceStats> = vec![SourceStats::default(); sources.len()];
+    let inner: SelectAll<BoxStream<'static, (usize, Result<T, RpcError>)>> = sources
+        .into_iter()
+        .enumerate()
+        .map(|(source, stream)| stream.map(move |item| (source, item)).boxed())
+        .collect();
+
+    MergedNotifications { inner, emitted: VecDeque::with_capacity(DEDUP_WINDOW), latest_slot: None, stats }
+}
+
+#[allow(dead_code)]
+impl<T: MergeableNotification> MergedNotifications<T> {
+    /// Indexed like the `sources` passed to `merge_notifications`.
+    pub fn stats(&self) -> &[SourceStats] {
+        &self.stats
+    }
+
+    /// `true` => emit.
+    fn accept(&mut self, source: usize, notification: &T) -> bool {
+        let (slot, hash) = (notification.slot(), notification.content_hash());
+        let now: Instant = Instant::now();
+        let stats: &mut SourceStats = &mut self.stats[source];
+
+        if let Some(first) = self.emitted.iter().find(|emitted| emitted.slot == slot && emitted.hash == hash) {
+            stats.late += 1;
+            stats.lag += now - first.at;
+            return false;
+        }
+        if self.latest_slot.is_some_and(|latest| slot < latest) {
+            log::debug!("Source {source} delivered slot {slot}, already at {:?}", self.latest_slot);
+            stats.stale += 1;
+            return false;
+        }
+
+        stats.first += 1;
+        self.latest_slot = Some(slot);
+        if self.emitted.len() == DEDUP_WINDOW {
+            self.emitted.pop_front();
+        }
+        self.emitted.push_back(Emitted { slot, hash, at: now });
+        true
+    }
+}
+
+impl<T: MergeableNotification> Stream for MergedNotifications<T> {
+    type Item = Result<Merged<T>, RpcError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some((source, Ok(notification)))) => {
+                    if self.accept(source, &notification) {
+                        return Poll::Ready(Some(Ok(Merged { source, notification })));
+                    }
+                },
+                Poll::Ready(Some((source, Err(e)))) => {
+                    log::warn!("Merged source {source} failed: {e}");
+                    return Poll::Ready(Some(Err(e)));
+                },
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
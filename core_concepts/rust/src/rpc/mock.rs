@@ -0,0 +1,311 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use futures_util::{StreamExt, SinkExt};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::{
+    accept_async,
+    tungstenite::protocol::{
+        Message,
+        CloseFrame,
+        frame::coding::CloseCode,
+    },
+};
+
+/// ### Canned answer of the mock HTTP endpoint for a single method
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    Result(serde_json::Value),
+    Error { code: i64, message: String },
+    /// Whole HTTP response gets this status, e.g. 429 with `Retry-After: 1`
+    HttpStatus { status: u16, retry_after: Option<u64> },
+}
+
+/// ### One step of a scripted WebSocket session
+#[derive(Debug, Clone)]
+pub enum WsStep {
+    /// Waits for the next request and confirms it with this subscription id
+    Confirm(u64),
+    /// Waits for the next request and answers it with an arbitrary result (e.g. `true` for unsubscribe)
+    Respond(serde_json::Value),
+    /// Waits for the next request and answers it with a JSON-RPC error
+    #[allow(dead_code)]
+    Reject { code: i64, message: String },
+    /// Sends a Text Frame as is (see `notification`)
+    Notify(serde_json::Value),
+    Ping,
+    /// Sends a Close Frame (Normal) and waits for the client to answer
+    Close,
+    /// Drops the TCP connection without any Close Frame
+    Disconnect,
+    Sleep(Duration),
+}
+
+/// ### In-process Solana RPC node for tests
+/// HTTP JSON-RPC and PubSub are served on two ephemeral ports of 127.0.0.1.
+/// - HTTP: every method answers with its queue of `MockResponse`s, the last one is repeated forever.
+///   Batches are answered in REVERSED order, so clients have to match responses by `id`.
+/// - WS: every new connection plays the next script pushed with `push_ws_session`,
+///   once the script is over the connection stays open (answering Pings) until the client leaves.
+///   No scripts left => idle connection.
+pub struct MockServer {
+    http_addr: SocketAddr,
+    ws_addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    responses: HashMap<String, VecDeque<MockResponse>>,
+    ws_sessions: VecDeque<Vec<WsStep>>,
+    requests: Vec<serde_json::Value>,
+    ws_connections: usize,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let http_listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server: MockServer = MockServer {
+            http_addr: http_listener.local_addr().unwrap(),
+            ws_addr: ws_listener.local_addr().unwrap(),
+            state: Arc::new(Mutex::new(MockState::default())),
+        };
+
+        let state: Arc<Mutex<MockState>> = Arc::clone(&server.state);
+        tokio::task::spawn(async move {
+            while let Ok((stream, _)) = http_listener.accept().await {
+                tokio::task::spawn(serve_http(stream, Arc::clone(&state)));
+            }
+        });
+
+        let state: Arc<Mutex<MockState>> = Arc::clone(&server.state);
+        tokio::task::spawn(async move {
+            while let Ok((stream, _)) = ws_listener.accept().await {
+                tokio::task::spawn(serve_ws(stream, Arc::clone(&state)));
+            }
+        });
+
+        server
+    }
+
+    pub fn http_url(&self) -> String {
+        format!("http://{}", self.http_addr)
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}", self.ws_addr)
+    }
+
+    /// Appends a response to the queue of the method.
+    pub fn on(&self, method: &str, response: MockResponse) -> &Self {
+        self.state.lock().unwrap().responses.entry(method.to_string()).or_default().push_back(response);
+        self
+    }
+
+    pub fn push_ws_session(&self, script: Vec<WsStep>) -> &Self {
+        self.state.lock().unwrap().ws_sessions.push_back(script);
+        self
+    }
+
+    /// Every JSON-RPC request received so far (HTTP and WS, batches are flattened).
+    pub fn requests(&self) -> Vec<serde_json::Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn requests_of(&self, method: &str) -> usize {
+        self.requests().iter().filter(|req| req["method"] == method).count()
+    }
+
+    pub fn ws_connections(&self) -> usize {
+        self.state.lock().unwrap().ws_connections
+    }
+}
+
+/// `{"jsonrpc":"2.0","method":<method>,"params":{"result":<result>,"subscription":<subscription>}}`
+pub fn notification(method: &str, subscription: u64, result: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": { "result": result, "subscription": subscription }
+    })
+}
+
+/// accountNotification / getAccountInfo value with base64 data
+pub fn account_value(lamports: u64, data_base64: &str) -> serde_json::Value {
+    serde_json::json!({
+        "lamports": lamports,
+        "owner": "wLdqJZg7heBecsP3vT57smP3yfVEa8mfyttaEagCeg5",
+        "data": [data_base64, "base64"],
+        "executable": false,
+        "rentEpoch": 18446744073709551615u64,
+        "space": 24
+    })
+}
+
+pub fn with_context(slot: u64, value: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "context": { "slot": slot }, "value": value })
+}
+
+impl MockState {
+    fn next_response(&mut self, method: &str) -> MockResponse {
+        match self.responses.get_mut(method) {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(queue) if !queue.is_empty() => queue[0].clone(),
+            _ => MockResponse::Error { code: -32601, message: "Method not found".into() },
+        }
+    }
+}
+
+async fn serve_http(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let (read, mut write) = stream.into_split();
+    let mut read: BufReader<tokio::net::tcp::OwnedReadHalf> = BufReader::new(read);
+
+    // keep-alive: many requests per connection
+    loop {
+        let mut content_length: usize = 0;
+        let mut line: String = String::new();
+        // request line + headers
+        loop {
+            line.clear();
+            match read.read_line(&mut line).await {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {},
+            }
+            if line == "\r\n" { break; }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let mut body: Vec<u8> = vec![0; content_length];
+        if read.read_exact(&mut body).await.is_err() { return; }
+
+        let (status, retry_after, res_body) = respond(&body, &state);
+        let mut res: String = format!("HTTP/1.1 {status} MOCK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n", res_body.len());
+        if let Some(secs) = retry_after {
+            res.push_str(&format!("retry-after: {secs}\r\n"));
+        }
+        res.push_str("\r\n");
+        res.push_str(&res_body);
+
+        if write.write_all(res.as_bytes()).await.is_err() { return; }
+    }
+}
+
+fn respond(body: &[u8], state: &Mutex<MockState>) -> (u16, Option<u64>, String) {
+    let request: serde_json::Value = match serde_json::from_slice(body) {
+        Ok(v) => v,
+        Err(_) => return (400, None, String::new()),
+    };
+    let mut state = state.lock().unwrap();
+
+    let (requests, is_batch): (Vec<serde_json::Value>, bool) = match request {
+        serde_json::Value::Array(requests) => (requests, true),
+        request => (vec![request], false),
+    };
+
+    let mut responses: Vec<serde_json::Value> = Vec::with_capacity(requests.len());
+    for request in requests.iter().rev() {
+        let method: &str = request["method"].as_str().unwrap_or_default();
+        match state.next_response(method) {
+            MockResponse::Result(result) => responses.push(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })),
+            MockResponse::Error { code, message } => responses.push(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": code, "message": message }
+            })),
+            MockResponse::HttpStatus { status, retry_after } => {
+                state.requests.extend(requests.iter().cloned());
+                return (status, retry_after, "Too many requests for a specific RPC call".into());
+            },
+        }
+    }
+    state.requests.extend(requests);
+
+    let res_body: serde_json::Value = match is_batch {
+        true => serde_json::Value::Array(responses),
+        false => responses.pop().unwrap_or_default(),
+    };
+    (200, None, res_body.to_string())
+}
+
+async fn serve_ws(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let Ok(ws_stream) = accept_async(stream).await else { return; };
+    let (mut write, mut read) = ws_stream.split();
+
+    let script: Vec<WsStep> = {
+        let mut state = state.lock().unwrap();
+        state.ws_connections += 1;
+        state.ws_sessions.pop_front().unwrap_or_default()
+    };
+
+    for step in script {
+        let request: Option<serde_json::Value> = match step {
+            WsStep::Confirm(_) | WsStep::Respond(_) | WsStep::Reject { .. } => {
+                let Some(request) = next_request(&mut read, &state).await else { return; };
+                Some(request)
+            },
+            _ => None,
+        };
+
+        let res: Result<(), tokio_tungstenite::tungstenite::Error> = match (step, request) {
+            (WsStep::Confirm(subscription), Some(request)) => {
+                write.send(Message::text(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": subscription }).to_string())).await
+            },
+            (WsStep::Respond(result), Some(request)) => {
+                write.send(Message::text(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }).to_string())).await
+            },
+            (WsStep::Reject { code, message }, Some(request)) => {
+                write.send(Message::text(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": code, "message": message }
+                }).to_string())).await
+            },
+            (WsStep::Notify(notification), _) => write.send(Message::text(notification.to_string())).await,
+            (WsStep::Ping, _) => write.send(Message::Ping(vec![].into())).await,
+            (WsStep::Close, _) => {
+                let frame: CloseFrame = CloseFrame { code: CloseCode::Normal, reason: "bye".into() };
+                let _ = write.send(Message::Close(Some(frame))).await;
+                // let the client answer the closing handshake
+                while let Some(Ok(_)) = read.next().await {}
+                return;
+            },
+            (WsStep::Disconnect, _) => return,  // both halves dropped => TCP connection is gone
+            (WsStep::Sleep(d), _) => {
+                tokio::time::sleep(d).await;
+                Ok(())
+            },
+            _ => Ok(()),
+        };
+        if res.is_err() { return; }
+    }
+
+    // script is over, keep the socket open: record requests, Pings are answered by tungstenite itself
+    while let Some(request) = next_request(&mut read, &state).await {
+        log::debug!("mock ws received: {request}");
+    }
+}
+
+async fn next_request<S>(read: &mut S, state: &Mutex<MockState>) -> Option<serde_json::Value>
+where
+    S: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin
+{
+    while let Some(Ok(msg)) = read.next().await {
+        if let Message::Text(text) = msg {
+            let request: serde_json::Value = serde_json::from_str(&text).ok()?;
+            state.lock().unwrap().requests.push(request.clone());
+            return Some(request);
+        }
+    }
+    None
+}
@@ -1 +1,24 @@
-pub mod communication;
\ No newline at end of file
+// the rpc module is a toolbox, main.rs only showcases a part of it
+
+pub mod anchor;
+pub mod backfill;
+pub mod batch;
+pub mod cache;
+pub mod cassette;
+pub mod client;
+pub mod coalesce;
+pub mod communication;
+pub mod config;
+pub mod encoding;
+pub mod error;
+pub mod failover;
+pub mod keepalive;
+pub mod logs;
+pub mod merge;
+#[cfg(test)]
+pub mod mock;
+pub mod pubsub;
+pub mod retry;
+pub mod sender;
+pub mod subscription;
+pub mod types;
@@ -0,0 +1,536 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures_util::{Stream, StreamExt, SinkExt};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use super::{
+    cassette::{self, Cassette, WsConnection},
+    communication::{CommitmentLevel, try_to_close_connection},
+    config::{AccountInfoConfig, LogsSubscribeConfig, SignatureSubscribeConfig},
+    encoding::UiEncoding,
+    error::RpcError,
+    keepalive::{
+        Keepalive,
+        KeepaliveAction,
+        KeepaliveConfig,
+    },
+    subscription::SUBSCRIPTION_BUFFER,
+    types::{
+        RpcResponse,
+        RpcNotification,
+        AccountNotification,
+        LogsNotification,
+        ProgramNotification,
+        SignatureNotification,
+        SlotInfo,
+        BlockNotification,
+    },
+};
+
+/// ### Multiplexed PubSub client
+/// Many subscriptions share ONE WebSocket (providers cap connections per API key).
+/// The socket is owned by a background task, which:
+/// - assigns JSON-RPC ids to the subscribe requests and maps them to server-assigned subscription ids,
+/// - routes every `*Notification` to the channel of its subscriber,
+/// - sends `*Unsubscribe`, when a subscriber handle is dropped.
+///
+/// Cloning is cheap, clones talk to the same background task.
+/// The task exits (closing the socket), when the client and all of its subscription handles are dropped.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct PubsubClient {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+/// ### Handle of a single subscription on the shared socket
+/// Dropping it unsubscribes on the server.
+/// Up to `SUBSCRIPTION_BUFFER` notifications are buffered. A subscriber, that falls further behind, is closed:
+/// the stream yields `Err(RpcError::Lagged)` and ends (and the server is unsubscribed), so a slow subscriber never
+/// stalls the others nor grows the memory, and never silently misses an update.
+#[allow(dead_code)]
+pub struct PubsubSubscription<T> {
+    key: u64,
+    rx: mpsc::Receiver<Result<serde_json::Value, RpcError>>,
+    commands: mpsc::UnboundedSender<Command>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[allow(dead_code)]
+enum Command {
+    Subscribe {
+        request: SubscribeRequest,
+        notifications: mpsc::Sender<Result<serde_json::Value, RpcError>>,
+        ack: oneshot::Sender<Result<u64, RpcError>>,
+    },
+    Unsubscribe {
+        key: u64,
+    },
+}
+
+#[allow(dead_code)]
+struct SubscribeRequest {
+    method: &'static str,       // e.g. "accountSubscribe"
+    params: serde_json::Value,
+    unsubscribe: &'static str,  // e.g. "accountUnsubscribe"
+    one_shot: bool,             // server cancels the subscription itself after the first notification
+}
+
+#[allow(dead_code)]
+struct SubscriptionEntry {
+    unsubscribe: &'static str,
+    one_shot: bool,
+    server_id: Option<u64>,  // None until the server confirms the subscription
+    notifications: mpsc::Sender<Result<serde_json::Value, RpcError>>,
+}
+
+/// ### logsSubscribe filter
+/// Serialized as `"all"`, `"allWithVotes"` or `{ "mentions": ["<pubkey>"] }` (the node accepts exactly one pubkey).
+#[allow(dead_code)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogsFilter {
+    All,
+    AllWithVotes,
+    Mentions(Vec<String>),
+}
+
+/// ### programSubscribe filters
+/// Serialized as `{ "memcmp": { ... } }` / `{ "dataSize": N }`, account has to match ALL of them.
+#[allow(dead_code)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgramFilter {
+    Memcmp(Memcmp),
+    DataSize(u64),
+}
+
+/// Compares `bytes` against account data at `offset`.
+#[allow(dead_code)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Memcmp {
+    pub offset: usize,
+    pub bytes: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<MemcmpEncoding>,  // base58 if omitted
+}
+
+#[allow(dead_code)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemcmpEncoding {
+    Base58,
+    Base64,
+}
+
+/// ### blockSubscribe filter
+#[allow(dead_code)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockFilter {
+    All,
+    MentionsAccountOrProgram(String),
+}
+
+/// Level of transaction detail in block notifications.
+#[allow(dead_code)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionDetails {
+    Full,
+    Signatures,
+    None,
+}
+
+#[allow(dead_code)]
+impl PubsubClient {
+    pub async fn connect(url: &str) -> Result<Self, RpcError> {
+        Self::connect_with_keepalive(url, KeepaliveConfig::default()).await
+    }
+
+    /// If the connection dies (Pong / idle timeout), every subscription gets the error as the last item of its stream.
+    /// `idle_timeout` counts notifications of ALL subscriptions on the socket.
+    pub async fn connect_with_keepalive(url: &str, keepalive: KeepaliveConfig) -> Result<Self, RpcError> {
+        Self::connect_with(url, keepalive, None).await
+    }
+
+    /// Records the inbound frames into the cassette or, in replay mode, plays a recorded connection back, see `Cassette`.
+    pub async fn connect_with_cassette(url: &str, keepalive: KeepaliveConfig, cassette: Cassette) -> Result<Self, RpcError> {
+        Self::connect_with(url, keepalive, Some(&cassette)).await
+    }
+
+    async fn connect_with(url: &str, keepalive: KeepaliveConfig, cassette: Option<&Cassette>) -> Result<Self, RpcError> {
+        let ws_stream: WsConnection = cassette::connect_ws(url, cassette).await?;
+
+        // unbounded on purpose: it only carries (un)subscribe commands, one per handle, and `Drop` can't await a free slot
+        let (commands, commands_rx) = mpsc::unbounded_channel::<Command>();
+        tokio::task::spawn(run(ws_stream, commands_rx, keepalive));
+
+        Ok(Self { commands })
+    }
+
+    /// `config` is the same as for getAccountInfo, see `AccountInfoConfig::for_subscription` for the defaults.
+    pub async fn account_subscribe(&self, pubkey: &str, config: AccountInfoConfig) -> Result<PubsubSubscription<AccountNotification>, RpcError> {
+        let params: serde_json::Value = serde_json::json!([pubkey, config.for_subscription()]);
+        self.subscribe("accountSubscribe", params, "accountUnsubscribe").await
+    }
+
+    /// Transaction logs, see `LogsFilter`.
+    pub async fn logs_subscribe(&self, filter: LogsFilter, config: LogsSubscribeConfig) -> Result<PubsubSubscription<LogsNotification>, RpcError> {
+        let params: serde_json::Value = serde_json::json!([filter, config]);
+        self.subscribe("logsSubscribe", params, "logsUnsubscribe").await
+    }
+
+    /// Every change of every account owned by `program_id`, that matches all of the `filters`.
+    pub async fn program_subscribe(
+        &self,
+        program_id: &str,
+        filters: Vec<ProgramFilter>,
+        config: AccountInfoConfig
+    ) -> Result<PubsubSubscription<ProgramNotification>, RpcError> {
+        let mut options: serde_json::Value = serde_json::json!(config.for_subscription());
+        options["filters"] = serde_json::json!(filters);
+        let params: serde_json::Value = serde_json::json!([program_id, options]);
+        self.subscribe("programSubscribe", params, "programUnsubscribe").await
+    }
+
+    /// ### One-shot subscription
+    /// Yields exactly one notification, when the transaction reaches `commitment`, then the stream ends.
+    pub async fn signature_subscribe(
+        &self,
+        signature: &str,
+        config: SignatureSubscribeConfig
+    ) -> Result<PubsubSubscription<SignatureNotification>, RpcError> {
+        let mut options: serde_json::Value = serde_json::json!(config);
+        options["enableReceivedNotification"] = serde_json::json!(false);
+        let params: serde_json::Value = serde_json::json!([signature, options]);
+        self.subscribe_with("signatureSubscribe", params, "signatureUnsubscribe", true).await
+    }
+
+    pub async fn slot_subscribe(&self) -> Result<PubsubSubscription<SlotInfo>, RpcError> {
+        self.subscribe("slotSubscribe", serde_json::json!([]), "slotUnsubscribe").await
+    }
+
+    /// Yields the new root slot every time the node sets one.
+    pub async fn root_subscribe(&self) -> Result<PubsubSubscription<u64>, RpcError> {
+        self.subscribe("rootSubscribe", serde_json::json!([]), "rootUnsubscribe").await
+    }
+
+    /// NOTE: unstable method, most providers have it disabled (or require `--rpc-pubsub-enable-block-subscription`).
+    pub async fn block_subscribe(
+        &self,
+        filter: BlockFilter,
+        transaction_details: TransactionDetails,
+        commitment: CommitmentLevel,
+        encoding: UiEncoding
+    ) -> Result<PubsubSubscription<BlockNotification>, RpcError> {
+        let params: serde_json::Value = serde_json::json!([
+            filter,
+            {
+                "commitment": commitment,
+                "encoding": encoding,
+                "transactionDetails": transaction_details,
+                "showRewards": false,
+                "maxSupportedTransactionVersion": 0
+            }
+        ]);
+        self.subscribe("blockSubscribe", params, "blockUnsubscribe").await
+    }
+
+    /// Resolves once the server confirmed the subscription (JSON-RPC rejection is returned as `Err`).
+    pub async fn subscribe<T: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: serde_json::Value,
+        unsubscribe: &'static str
+    ) -> Result<PubsubSubscription<T>, RpcError> {
+        self.subscribe_with(method, params, unsubscribe, false).await
+    }
+
+    async fn subscribe_with<T: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: serde_json::Value,
+        unsubscribe: &'static str,
+        one_shot: bool
+    ) -> Result<PubsubSubscription<T>, RpcError> {
+        // +1 is reserved for the final item (a one-shot result, `Lagged` or the connection error), see `Router::on_notification`
+        let (notifications, rx) = mpsc::channel(SUBSCRIPTION_BUFFER + 1);
+        let (ack, ack_rx) = oneshot::channel();
+
+        let request: SubscribeRequest = SubscribeRequest { method, params, unsubscribe, one_shot };
+        self.commands
+            .send(Command::Subscribe { request, notifications, ack })
+            .map_err(|_| RpcError::WsClosed(None))?;
+
+        let key: u64 = ack_rx.await.map_err(|_| RpcError::WsClosed(None))??;
+
+        Ok(PubsubSubscription { key, rx, commands: self.commands.clone(), _marker: PhantomData })
+    }
+}
+
+#[allow(dead_code)]
+impl<T> PubsubSubscription<T> {
+    /// Client-side key of the subscription (stays the same, even though the server id is an implementation detail).
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+}
+
+impl<T: DeserializeOwned> Stream for PubsubSubscription<T> {
+    type Item = Result<T, RpcError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx).map(|item| {
+            item.map(|res| res.and_then(|value| serde_json::from_value::<T>(value).map_err(RpcError::from)))
+        })
+    }
+}
+
+impl<T> Drop for PubsubSubscription<T> {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Unsubscribe { key: self.key });
+    }
+}
+
+/// ### State of the background task
+/// `key` is the JSON-RPC id of the original subscribe request, it identifies the subscription on our side.
+#[allow(dead_code)]
+#[derive(Default)]
+struct Router {
+    next_id: u64,
+    subscriptions: HashMap<u64, SubscriptionEntry>,  // key => entry
+    by_server_id: HashMap<u64, u64>,                  // server subscription id => key
+    pending_subscribe: HashMap<u64, oneshot::Sender<Result<u64, RpcError>>>,  // request id (== key) => waiting caller
+    pending_unsubscribe: HashMap<u64, u64>,           // request id => server subscription id
+}
+
+#[allow(dead_code)]
+impl Router {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Returns the request, that has to be written to the socket (if any).
+    fn on_command(&mut self, command: Command) -> Option<serde_json::Value> {
+        match command {
+            Command::Subscribe { request, notifications, ack } => {
+                let key: u64 = self.next_id();
+                self.subscriptions.insert(key, SubscriptionEntry {
+                    unsubscribe: request.unsubscribe,
+                    one_shot: request.one_shot,
+                    server_id: None,
+                    notifications
+                });
+                self.pending_subscribe.insert(key, ack);
+                Some(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": key,
+                    "method": request.method,
+                    "params": request.params
+                }))
+            },
+            Command::Unsubscribe { key } => {
+                let entry: SubscriptionEntry = self.subscriptions.remove(&key)?;
+                let server_id: u64 = entry.server_id?;
+                self.by_server_id.remove(&server_id);
+                Some(self.unsubscribe_request(entry.unsubscribe, server_id))
+            },
+        }
+    }
+
+    fn unsubscribe_request(&mut self, method: &'static str, server_id: u64) -> serde_json::Value {
+        let id: u64 = self.next_id();
+        self.pending_unsubscribe.insert(id, server_id);
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": [server_id]
+        })
+    }
+
+    /// Returns the request, that has to be written to the socket (if any).
+    fn on_text(&mut self, text: &str) -> Option<serde_json::Value> {
+        match serde_json::from_str::<RpcResponse<serde_json::Value>>(text) {
+            Ok(res) if res.id.is_some() => self.on_response(res),
+            _ => {
+                match serde_json::from_str::<RpcNotification<serde_json::Value>>(text) {
+                    Ok(notification) => self.on_notification(notification),
+                    Err(_) => {
+                        log::warn!("Unexpected message: {text}");
+                        None
+                    },
+                }
+            }
+        }
+    }
+
+    fn on_response(&mut self, res: RpcResponse<serde_json::Value>) -> Option<serde_json::Value> {
+        let id: u64 = res.id?;
+
+        if let Some(server_id) = self.pending_unsubscribe.remove(&id) {
+            match res.error {
+                Some(e) => log::warn!("Failed to unsubscribe {server_id}: {e}"),
+                None => log::debug!("Unsubscribed {server_id}"),
+            }
+            return None;
+        }
+
+        let ack: oneshot::Sender<Result<u64, RpcError>> = self.pending_subscribe.remove(&id)?;
+        let key: u64 = id;
+
+        let server_id: u64 = match (res.result.and_then(|v| v.as_u64()), res.error) {
+            (_, Some(e)) => {
+                self.subscriptions.remove(&key);
+                let _ = ack.send(Err(e.into()));
+                return None;
+            },
+            (Some(server_id), None) => server_id,
+            (None, None) => {
+                self.subscriptions.remove(&key);
+                let _ = ack.send(Err(RpcError::EmptyResult("subscribe".into())));
+                return None;
+            },
+        };
+
+        // caller gave up (dropped the `subscribe` future) before the confirmation arrived => nobody will ever read it
+        if ack.send(Ok(key)).is_err() {
+            let entry: SubscriptionEntry = self.subscriptions.remove(&key)?;
+            return Some(self.unsubscribe_request(entry.unsubscribe, server_id));
+        }
+
+        if let Some(entry) = self.subscriptions.get_mut(&key) {
+            entry.server_id = Some(server_id);
+            self.by_server_id.insert(server_id, key);
+        }
+        None
+    }
+
+    /// Returns the unsubscribe request of a subscriber, that fell behind (if any).
+    fn on_notification(&mut self, notification: RpcNotification<serde_json::Value>) -> Option<serde_json::Value> {
+        let server_id: u64 = notification.params.subscription;
+        let Some(&key) = self.by_server_id.get(&server_id) else {
+            // late notification for a subscription, that was just dropped
+            log::debug!("{} for unknown subscription {server_id}", notification.method);
+            return None;
+        };
+        let entry: &SubscriptionEntry = self.subscriptions.get(&key)?;
+
+        // never awaited, the router serves every subscription of the socket.
+        // the last slot is kept for the final item, so a one-shot result always fits
+        if entry.one_shot {
+            let _ = entry.notifications.try_send(Ok(notification.params.result));
+            // server already forgot the subscription, dropping the sender ends the subscriber's stream
+            self.subscriptions.remove(&key);
+            self.by_server_id.remove(&server_id);
+            return None;
+        }
+        if entry.notifications.capacity() > 1 {
+            let _ = entry.notifications.try_send(Ok(notification.params.result));
+            return None;
+        }
+
+        log::warn!("Subscriber of {} (server id {server_id}) is {SUBSCRIPTION_BUFFER} notifications behind, closing it", notification.method);
+        let _ = entry.notifications.try_send(Err(RpcError::Lagged(SUBSCRIPTION_BUFFER)));
+        self.on_command(Command::Unsubscribe { key })
+    }
+
+    /// Socket is gone => every subscriber (and every caller waiting for a confirmation) gets the error, streams end.
+    fn fail_all(&mut self, reason: &RpcError) {
+        for (_, ack) in self.pending_subscribe.drain() {
+            let _ = ack.send(Err(reason.fan_out()));
+        }
+        // fits into the reserved slot
+        for (_, entry) in self.subscriptions.drain() {
+            let _ = entry.notifications.try_send(Err(reason.fan_out()));
+        }
+        self.by_server_id.clear();
+    }
+}
+
+#[allow(dead_code)]
+async fn run(
+    ws_stream: WsConnection,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    keepalive: KeepaliveConfig
+) {
+    let (mut write, mut read) = ws_stream.split();
+    let mut router: Router = Router::default();
+    let mut keepalive: Keepalive = Keepalive::new(keepalive);
+
+    let reason: RpcError = loop {
+        let outgoing: Option<serde_json::Value> = tokio::select! {
+            command = commands.recv() => {
+                match command {
+                    Some(command) => router.on_command(command),
+                    // client and all handles are dropped
+                    None => {
+                        try_to_close_connection(&mut write, None).await;
+                        return;
+                    }
+                }
+            },
+
+            msg = read.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        keepalive.on_notification();
+                        router.on_text(&text)
+                    },
+                    Some(Ok(Message::Ping(v))) => {
+                        if write.send(Message::Pong(v)).await.is_err() {
+                            log::error!("Failed to send Pong Frame!");
+                        }
+                        None
+                    },
+                    Some(Ok(Message::Pong(_))) => {
+                        keepalive.on_pong();
+                        None
+                    },
+                    Some(Ok(Message::Close(frame))) => {
+                        log::error!("Received Close Frame! Closing stream.");
+                        try_to_close_connection(&mut write, frame.clone()).await;
+                        break RpcError::WsClosed(frame);
+                    },
+                    Some(Ok(_)) => None,
+                    Some(Err(e)) => {
+                        log::error!("PubSub connection failed: {e}");
+                        break e.into();
+                    },
+                    None => break RpcError::WsClosed(None),
+                }
+            },
+
+            action = keepalive.tick() => {
+                match action {
+                    KeepaliveAction::Ping => {
+                        if let Err(e) = write.send(Message::Ping(vec![].into())).await {
+                            log::error!("Failed to send a Heartbeat Ping! {e}");
+                        }
+                        None
+                    },
+                    KeepaliveAction::Dead(e) => {
+                        log::error!("{e}");
+                        break e;
+                    }
+                }
+            }
+        };
+
+        if let Some(request_json_rpc) = outgoing {
+            if let Err(e) = write.send(Message::text(request_json_rpc.to_string())).await {
+                log::error!("Failed to send a request! {e}");
+                break e.into();
+            }
+        }
+    };
+
+    router.fail_all(&reason);
+}
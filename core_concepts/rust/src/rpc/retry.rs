@@ -0,0 +1,148 @@
+use std::{
+    sync::Mutex,
+    time::Duration,
+};
+use rand::Rng;
+use tokio::time::Instant;
+
+use super::error::RpcError;
+
+/// ### Decides whether (and when) a failed HTTP request is repeated
+/// `attempt` is the number of attempts made so far (starts from 1).
+/// Return `None` to give up and hand the error to the caller.
+pub trait RetryPolicy: Send + Sync {
+    fn next_delay(&self, attempt: u32, error: &RpcError) -> Option<Duration>;
+}
+
+/// Never retries, the default of `RpcClient`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoRetry;
+
+impl RetryPolicy for NoRetry {
+    fn next_delay(&self, _attempt: u32, _error: &RpcError) -> Option<Duration> {
+        None
+    }
+}
+
+/// ### Exponential backoff with jitter
+/// Retries only errors classified as transient (`RpcError::is_retryable`).
+/// On 429 the `Retry-After` header wins over the computed delay, but it's capped at `max_backoff` as well,
+/// so a misbehaving endpoint can't stall the caller for hours.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    pub max_attempts: u32,  // including the first one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&self, attempt: u32, error: &RpcError) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.is_retryable() {
+            return None;
+        }
+        if let RpcError::HttpStatus { retry_after: Some(retry_after), .. } = error {
+            return Some((*retry_after).min(self.max_backoff));
+        }
+        Some(backoff_with_jitter(self.initial_backoff, self.max_backoff, attempt))
+    }
+}
+
+/// delay(attempt) = min(max, initial * 2^(attempt - 1)), then randomized within [delay / 2, delay].
+/// `attempt` starts from 1.
+#[allow(dead_code)]
+pub fn backoff_with_jitter(initial: Duration, max: Duration, attempt: u32) -> Duration {
+    let exp: u32 = attempt.saturating_sub(1).min(31);
+    let delay: Duration = initial
+        .saturating_mul(1 << exp)
+        .min(max);
+
+    let half: Duration = delay / 2;
+    half + half.mul_f64(rand::rng().random::<f64>())
+}
+
+/// ### Client-side token bucket
+/// Allows bursts of up to `burst` requests, then `requests_per_second` on average,
+/// so we stay under the provider's quota instead of collecting 429s.
+///
+/// Every caller reserves a token right away (the balance may go negative) and sleeps off the deficit,
+/// so waiting callers are served in the order they came. A caller dropped while sleeping (timeout, `select!`)
+/// gives its token back.
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[allow(dead_code)]
+impl RateLimiter {
+    /// Panics if `requests_per_second` isn't a finite positive number, the waits couldn't be computed.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        assert!(
+            requests_per_second.is_finite() && requests_per_second > 0.0,
+            "RateLimiter: requests_per_second must be finite and > 0, got {requests_per_second}"
+        );
+        let burst: f64 = f64::from(burst.max(1));
+        Self {
+            requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket { tokens: burst, last_refill: Instant::now() }),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        let wait: Duration = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now: Instant = Instant::now();
+            let refill: f64 = now.duration_since(bucket.last_refill).as_secs_f64() * self.requests_per_second;
+            bucket.tokens = (bucket.tokens + refill).min(self.burst);
+            bucket.last_refill = now;
+
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / self.requests_per_second)
+        };
+
+        let reservation: Reservation = Reservation { limiter: self, used: false };
+        tokio::time::sleep(wait).await;
+        reservation.use_token();
+    }
+}
+
+/// Token taken by a sleeping caller, returned to the bucket unless the sleep completes.
+struct Reservation<'a> {
+    limiter: &'a RateLimiter,
+    used: bool,
+}
+
+impl Reservation<'_> {
+    fn use_token(mut self) {
+        self.used = true;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if !self.used {
+            let mut bucket = self.limiter.bucket.lock().unwrap();
+            bucket.tokens = (bucket.tokens + 1.0).min(self.limiter.burst);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::Duration;
+use futures_util::StreamExt;
+use tokio::time::{Interval, MissedTickBehavior};
+
+use super::{
+    client::RpcClient,
+    communication::CommitmentLevel,
+    config::{SendTransactionConfig, SignatureSubscribeConfig},
+    error::RpcError,
+    pubsub::{PubsubClient, PubsubSubscription},
+    types::{SignatureNotification, SignatureStatus},
+};
+
+/// ### Final outcome of a submitted transaction
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionStatus {
+    /// Landed without an error and reached (at least) the requested commitment
+    Confirmed { commitment: CommitmentLevel, slot: u64 },
+    /// Landed, but the execution failed, reached the requested commitment as well. Fees are charged anyway.
+    Failed { slot: u64, err: serde_json::Value },
+    /// The blockhash expired (block height > lastValidBlockHeight) before the tx landed, it can never land now
+    Expired,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Confirmation {
+    pub signature: String,
+    pub status: TransactionStatus,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct SenderConfig {
+    pub commitment: CommitmentLevel,  // level to wait for
+    pub send: SendTransactionConfig,
+    pub rebroadcast_interval: Duration,
+    pub poll_interval: Duration,  // getSignatureStatuses + getBlockHeight
+}
+
+impl Default for SenderConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentLevel::Confirmed,
+            // we rebroadcast ourselves, the node shouldn't do it on top of that
+            send: SendTransactionConfig { max_retries: Some(0), ..Default::default() },
+            rebroadcast_interval: Duration::from_secs(2),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// ### sendTransaction + confirmation tracking
+/// 1. The tx is sent (preflight errors are returned as `Err` right away).
+/// 2. The result is awaited via signatureSubscribe (if a `PubsubClient` is given) and by polling getSignatureStatuses,
+///    whichever comes first, polling alone keeps working if the socket dies.
+/// 3. Meanwhile the same tx is re-sent every `rebroadcast_interval` (skipping preflight), leaders drop txs under load.
+///    The rebroadcasting stops as soon as the tx has any status, i.e. it landed.
+/// 4. Once the block height passes `last_valid_block_height` without the tx, it's `Expired`.
+///
+/// `Confirmed` and `Failed` are both reported at `SenderConfig::commitment`, a failure seen at a lower level is awaited
+/// like a success, the block could still be dropped.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct TransactionSender {
+    client: RpcClient,
+    pubsub: Option<PubsubClient>,
+    config: SenderConfig,
+}
+
+#[allow(dead_code)]
+impl TransactionSender {
+    pub fn new(client: RpcClient) -> Self {
+        Self { client, pubsub: None, config: SenderConfig::default() }
+    }
+
+    pub fn with_pubsub(mut self, pubsub: PubsubClient) -> Self {
+        self.pubsub = Some(pubsub);
+        self
+    }
+
+    pub fn with_config(mut self, config: SenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// `transaction_base64` is a signed tx, `last_valid_block_height` comes with the blockhash it was signed with (getLatestBlockhash).
+    pub async fn send_and_confirm(&self, transaction_base64: &str, last_valid_block_height: u64) -> Result<Confirmation, RpcError> {
+        let signature: String = self.client.send_transaction_with_config(transaction_base64, self.config.send.clone()).await?;
+        log::info!("Transaction {signature} sent, waiting for {:?}", self.config.commitment);
+
+        let mut subscription: Option<PubsubSubscription<SignatureNotification>> = match &self.pubsub {
+            Some(pubsub) => match pubsub.signature_subscribe(&signature, SignatureSubscribeConfig { commitment: Some(self.config.commitment) }).await {
+                Ok(subscription) => Some(subscription),
+                Err(e) => {
+                    log::warn!("signatureSubscribe failed, falling back to polling: {e}");
+                    None
+                },
+            },
+            None => None,
+        };
+
+        // the tx was just sent, so both timers start one period later
+        let mut poll: Interval = interval_after(self.config.poll_interval);
+        let mut rebroadcast: Interval = interval_after(self.config.rebroadcast_interval);
+        let rebroadcast_config: SendTransactionConfig = SendTransactionConfig { skip_preflight: true, ..self.config.send.clone() };
+
+        let mut landed: bool = false;
+        let status: TransactionStatus = loop {
+            tokio::select! {
+                notification = next_notification(&mut subscription) => {
+                    match notification {
+                        Some(Ok(notification)) => break match notification.err {
+                            Some(err) => TransactionStatus::Failed { slot: notification.slot, err },
+                            None => TransactionStatus::Confirmed { commitment: self.config.commitment, slot: notification.slot },
+                        },
+                        Some(Err(e)) => {
+                            log::warn!("Signature subscription failed, polling only: {e}");
+                            subscription = None;
+                        },
+                        None => subscription = None,
+                    }
+                },
+
+                _ = poll.tick() => {
+                    match self.poll(&signature, last_valid_block_height).await {
+                        PollOutcome::Pending => {},
+                        PollOutcome::Landed => landed = true,
+                        PollOutcome::Done(status) => break status,
+                    }
+                },
+
+                _ = rebroadcast.tick(), if !landed => {
+                    if let Err(e) = self.client.send_transaction_with_config(transaction_base64, rebroadcast_config.clone()).await {
+                        log::warn!("Failed to rebroadcast {signature}: {e}");
+                    }
+                },
+            }
+        };
+
+        log::info!("Transaction {signature}: {status:?}");
+        Ok(Confirmation { signature, status })
+    }
+
+    /// Failed polls are only logged as `Pending`, the next tick retries.
+    async fn poll(&self, signature: &str, last_valid_block_height: u64) -> PollOutcome {
+        match self.client.get_signature_statuses(&[signature]).await {
+            Ok(statuses) => {
+                let status: Option<SignatureStatus> = statuses.into_iter().next().flatten();
+                // landed (even if below the target commitment) => it can't expire anymore
+                if let Some(status) = status {
+                    if !self.reached(&status) {
+                        return PollOutcome::Landed;
+                    }
+                    return PollOutcome::Done(match status.err {
+                        Some(err) => TransactionStatus::Failed { slot: status.slot, err },
+                        None => TransactionStatus::Confirmed { commitment: self.config.commitment, slot: status.slot },
+                    });
+                }
+            },
+            Err(e) => {
+                log::warn!("getSignatureStatuses failed: {e}");
+                return PollOutcome::Pending;
+            },
+        }
+
+        // only a tx without any status can expire. Checked AFTER the status, so a tx that landed in the last valid block isn't reported as expired
+        match self.client.get_block_height().await {
+            Ok(block_height) if block_height > last_valid_block_height => PollOutcome::Done(TransactionStatus::Expired),
+            Ok(_) => PollOutcome::Pending,
+            Err(e) => {
+                log::warn!("getBlockHeight failed: {e}");
+                PollOutcome::Pending
+            },
+        }
+    }
+
+    fn reached(&self, status: &SignatureStatus) -> bool {
+        // no confirmationStatus (very old nodes) => rooted only if confirmations is null
+        let level: CommitmentLevel = status.confirmation_status.unwrap_or(match status.confirmations {
+            None => CommitmentLevel::Finalized,
+            Some(_) => CommitmentLevel::Processed,
+        });
+        level >= self.config.commitment
+    }
+}
+
+/// One getSignatureStatuses (+ getBlockHeight) round.
+enum PollOutcome {
+    Pending,
+    /// Has a status below the target commitment: no need to rebroadcast, but not final yet
+    Landed,
+    Done(TransactionStatus),
+}
+
+#[allow(dead_code)]
+fn interval_after(period: Duration) -> Interval {
+    let mut interval: Interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// Pending forever without a subscription, so the `select!` branch just never fires.
+#[allow(dead_code)]
+async fn next_notification(
+    subscription: &mut Option<PubsubSubscription<SignatureNotification>>
+) -> Option<Result<SignatureNotification, RpcError>> {
+    match subscription {
+        Some(subscription) => subscription.next().await,
+        None => std::future::pending().await,
+    }
+}
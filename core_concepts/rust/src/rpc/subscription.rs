@@ -0,0 +1,452 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use futures_util::{Stream, StreamExt, SinkExt};
+use serde::de::DeserializeOwned;
+use tokio::sync::{mpsc, watch};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use super::{
+    cassette::{self, Cassette, WsConnection},
+    client::RpcClient,
+    communication::try_to_close_connection,
+    config::AccountInfoConfig,
+    error::RpcError,
+    keepalive::{
+        Keepalive,
+        KeepaliveAction,
+        KeepaliveConfig,
+    },
+    merge::MergeableNotification,
+    retry::backoff_with_jitter,
+    types::{
+        RpcContextResponse,
+        RpcResponse,
+        RpcResponseError,
+        RpcNotification,
+        AccountNotification,
+    },
+};
+
+/// How many notifications may wait in the channel, before the background task stops reading the socket.
+pub(crate) const SUBSCRIPTION_BUFFER: usize = 1024;
+
+/// ### Handle of a running subscription
+/// Yields typed notifications, the socket itself is owned by a background task.
+/// Dropping the handle closes the channel, the task notices it, sends a Close Frame and exits.
+pub struct Subscription<T> {
+    rx: mpsc::Receiver<Result<T, RpcError>>,
+}
+
+impl<T> Subscription<T> {
+    pub(crate) fn new(rx: mpsc::Receiver<Result<T, RpcError>>) -> Self {
+        Self { rx }
+    }
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = Result<T, RpcError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// ### What a Text Frame turned out to be
+pub(crate) enum Frame<T> {
+    Subscribed(u64),  // server-assigned subscription id
+    Rejected(RpcResponseError),
+    Notification(Result<T, RpcError>),
+    Unknown,
+}
+
+/// Subscription confirmations carry an `id`, notifications carry the `method`.
+pub(crate) fn parse_frame<T: DeserializeOwned>(text: &str, notification_method: &str) -> Frame<T> {
+    if let Ok(res) = serde_json::from_str::<RpcResponse<u64>>(text) {
+        if res.id.is_some() {
+            return match (res.result, res.error) {
+                (_, Some(e)) => Frame::Rejected(e),
+                (Some(subscription), None) => Frame::Subscribed(subscription),
+                (None, None) => Frame::Unknown,
+            };
+        }
+    }
+
+    match serde_json::from_str::<RpcNotification<serde_json::Value>>(text) {
+        Ok(notification) if notification.method == notification_method => Frame::Notification(
+            serde_json::from_value::<T>(notification.params.result).map_err(RpcError::from)
+        ),
+        _ => Frame::Unknown,
+    }
+}
+
+/// ### Exponential backoff with jitter
+/// delay(attempt) = min(max_backoff, initial_backoff * 2^(attempt - 1)), then randomized within [delay / 2, delay].
+/// Jitter prevents all of our watchers from hammering the provider at the same moment after a mass disconnect.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub max_attempts: Option<u32>,  // None => retry forever
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl ReconnectPolicy {
+    /// `attempt` starts from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        backoff_with_jitter(self.initial_backoff, self.max_backoff, attempt)
+    }
+}
+
+/// ### Connection state changes of a supervised subscription
+/// `Reconnecting` => the socket is gone, notifications may be MISSED until `Resubscribed` arrives.
+/// `Resynced(slot)` => the notification of this slot was made from getAccountInfo (it's also marked with `resync` in the stream).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    Connected,
+    Reconnecting(u32),  // attempt number, starts from 1
+    Resubscribed,
+    Resynced(u64),
+}
+
+pub type ConnectErrorHook = Arc<dyn Fn(&RpcError) + Send + Sync>;
+
+/// ### Where a supervised subscription connects
+/// With an HTTP client of the same provider (or any other one) set via `with_resync`, every resubscription is followed by
+/// a getAccountInfo catch-up, see `account_subscribe_supervised`.
+/// With a cassette every connection is recorded (or replayed), see `Cassette`.
+/// `on_connect_error` is called with every failed connect to `ws_url` (used by `FailoverClient` to count them against the endpoint).
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct SubscriptionTarget {
+    pub ws_url: String,
+    pub resync: Option<RpcClient>,
+    pub cassette: Option<Cassette>,
+    pub on_connect_error: Option<ConnectErrorHook>,
+}
+
+#[allow(dead_code)]
+impl SubscriptionTarget {
+    pub fn new(ws_url: &str) -> Self {
+        Self { ws_url: ws_url.to_string(), resync: None, cassette: None, on_connect_error: None }
+    }
+
+    pub fn with_resync(mut self, client: RpcClient) -> Self {
+        self.resync = Some(client);
+        self
+    }
+
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    pub fn with_connect_error_hook(mut self, hook: impl Fn(&RpcError) + Send + Sync + 'static) -> Self {
+        self.on_connect_error = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl From<&str> for SubscriptionTarget {
+    fn from(ws_url: &str) -> Self {
+        Self::new(ws_url)
+    }
+}
+
+impl From<&String> for SubscriptionTarget {
+    fn from(ws_url: &String) -> Self {
+        Self::new(ws_url)
+    }
+}
+
+/// ### Supervised accountSubscribe
+/// Same as `communication::account_subscribe`, but the connection is re-established (and the subscription request is re-sent)
+/// whenever the provider drops the socket with a retryable error.
+/// The stream ends (with the last error) only if a non-retryable error occurred or `policy.max_attempts` is exhausted,
+/// to stop it earlier just drop the handle.
+///
+/// Updates made while the socket was down are never sent by the node. If the target has a `resync` client,
+/// after each resubscription the current state is fetched (`minContextSlot` = last delivered slot) and delivered in the
+/// stream with `resync: true`, if it differs from the last delivered one.
+/// Notifications older than the last delivered slot are dropped, so the state never goes back in time.
+#[allow(dead_code)]
+pub fn account_subscribe_supervised(
+    target: impl Into<SubscriptionTarget>,
+    pubkey: &str,
+    config: AccountInfoConfig,
+    policy: ReconnectPolicy,
+    keepalive: KeepaliveConfig,
+    events: mpsc::UnboundedSender<ConnectionEvent>
+) -> Subscription<AccountNotification> {
+    // the sender is dropped right away, so the target never changes
+    let (_, target_rx) = watch::channel(target.into());
+    account_subscribe_supervised_on(target_rx, pubkey, config, policy, keepalive, events)
+}
+
+/// ### Supervised accountSubscribe over a changing endpoint
+/// Every (re)connect goes to the current value of `target_rx`.
+/// When the value changes, the live session is closed and the subscription moves to the new target
+/// (used by `FailoverClient` to leave an unhealthy endpoint).
+#[allow(dead_code)]
+pub(crate) fn account_subscribe_supervised_on(
+    target_rx: watch::Receiver<SubscriptionTarget>,
+    pubkey: &str,
+    config: AccountInfoConfig,
+    policy: ReconnectPolicy,
+    keepalive: KeepaliveConfig,
+    events: mpsc::UnboundedSender<ConnectionEvent>
+) -> Subscription<AccountNotification> {
+    let config: AccountInfoConfig = config.for_subscription();
+    let account: SupervisedAccount = SupervisedAccount {
+        request_json_rpc: serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "accountSubscribe",
+            "params": [pubkey, config]
+        }),
+        pubkey: pubkey.to_string(),
+        config,
+        keepalive,
+    };
+
+    let mut target_rx: watch::Receiver<SubscriptionTarget> = target_rx;
+    let (tx, rx) = mpsc::channel::<Result<AccountNotification, RpcError>>(SUBSCRIPTION_BUFFER);
+
+    tokio::task::spawn(async move {
+        tokio::select! {
+            // handle dropped while we are connecting / backing off
+            _ = tx.closed() => {},
+            Err(e) = supervise(&mut target_rx, &account, policy, &tx, &events) => {
+                let _ = tx.send(Err(e)).await;
+            }
+        }
+    });
+
+    Subscription::new(rx)
+}
+
+/// What is subscribed, doesn't change between the sessions.
+#[allow(dead_code)]
+struct SupervisedAccount {
+    request_json_rpc: serde_json::Value,
+    pubkey: String,
+    config: AccountInfoConfig,  // with the defaults filled, so the catch-up fetches exactly what is subscribed
+    keepalive: KeepaliveConfig,
+}
+
+/// Carried over from one session to the next.
+#[allow(dead_code)]
+#[derive(Default)]
+struct SessionState {
+    attempt: u32,
+    subscribed: bool,  // whether any session got confirmed, i.e. whether the next one is a resubscription
+    last: Option<AccountNotification>,  // last delivered
+}
+
+/// Ok(()) => the consumer dropped the handle.
+#[allow(dead_code)]
+async fn supervise(
+    target_rx: &mut watch::Receiver<SubscriptionTarget>,
+    account: &SupervisedAccount,
+    policy: ReconnectPolicy,
+    tx: &mpsc::Sender<Result<AccountNotification, RpcError>>,
+    events: &mpsc::UnboundedSender<ConnectionEvent>
+) -> Result<(), RpcError> {
+    let mut state: SessionState = SessionState::default();
+    loop {
+        let e: RpcError = run_session(target_rx, account, &mut state, tx, events).await;
+        if tx.is_closed() {
+            return Ok(());
+        }
+        if !e.is_retryable() {
+            return Err(e);
+        }
+
+        state.attempt += 1;
+        let attempt: u32 = state.attempt;
+        if policy.max_attempts.is_some_and(|max| attempt > max) {
+            log::error!("Giving up on accountSubscribe after {} attempts!", attempt - 1);
+            return Err(e);
+        }
+
+        log::warn!("Subscription dropped: {e}. Reconnecting, attempt {attempt}.");
+        let _ = events.send(ConnectionEvent::Reconnecting(attempt));
+        tokio::time::sleep(policy.backoff(attempt)).await;
+    }
+}
+
+/// ### One connection lifetime
+/// Always ends with an error, which explains why the session is over (`WsClosed` if the server closed it properly,
+/// `PongTimeout` / `IdleTimeout` if the connection silently died, `EndpointUnhealthy` if the target changed).
+/// `state.attempt` is reset as soon as the subscription is confirmed, so the backoff starts from scratch after the next drop.
+#[allow(dead_code)]
+async fn run_session(
+    target_rx: &mut watch::Receiver<SubscriptionTarget>,
+    account: &SupervisedAccount,
+    state: &mut SessionState,
+    tx: &mpsc::Sender<Result<AccountNotification, RpcError>>,
+    events: &mpsc::UnboundedSender<ConnectionEvent>
+) -> RpcError {
+    let target: SubscriptionTarget = target_rx.borrow_and_update().clone();
+    let ws_stream: WsConnection = match cassette::connect_ws(&target.ws_url, target.cassette.as_ref()).await {
+        Ok(v) => v,
+        Err(e) => {
+            if let Some(on_connect_error) = &target.on_connect_error {
+                on_connect_error(&e);
+            }
+            return e;
+        },
+    };
+    let _ = events.send(ConnectionEvent::Connected);
+
+    let (mut write, mut read) = ws_stream.split();
+
+    if let Err(e) = write.send(Message::text(account.request_json_rpc.to_string())).await {
+        return e.into();
+    }
+
+    let mut keepalive: Keepalive = Keepalive::new(account.keepalive);
+    loop {
+        tokio::select! {
+            msg = read.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        match parse_frame::<AccountNotification>(&text, "accountNotification") {
+                            Frame::Subscribed(_) => {
+                                state.attempt = 0;
+                                let resubscribed: bool = state.subscribed;
+                                state.subscribed = true;
+                                if !resubscribed {
+                                    continue;
+                                }
+                                let _ = events.send(ConnectionEvent::Resubscribed);
+
+                                // frames arriving meanwhile wait in the socket, the older ones are dropped by `deliver`
+                                let Some(client) = &target.resync else { continue; };
+                                if let Some(fetched) = catch_up(client, account, state.last.as_ref()).await {
+                                    let _ = events.send(ConnectionEvent::Resynced(fetched.slot));
+                                    if deliver(state, tx, Ok(fetched)).await.is_err() {
+                                        try_to_close_connection(&mut write, None).await;
+                                        return RpcError::WsClosed(None);
+                                    }
+                                }
+                            },
+                            Frame::Rejected(e) => {
+                                try_to_close_connection(&mut write, None).await;
+                                return e.into();
+                            },
+                            Frame::Notification(notification) => {
+                                keepalive.on_notification();
+                                if deliver(state, tx, notification).await.is_err() {
+                                    try_to_close_connection(&mut write, None).await;
+                                    return RpcError::WsClosed(None);
+                                }
+                            },
+                            Frame::Unknown => log::warn!("Unexpected message: {text}"),
+                        }
+                    },
+                    Some(Ok(Message::Ping(v))) => {
+                        if write.send(Message::Pong(v)).await.is_err() {
+                            log::error!("Failed to send Pong Frame!");
+                        }
+                    },
+                    Some(Ok(Message::Pong(_))) => keepalive.on_pong(),
+                    Some(Ok(Message::Close(frame))) => {
+                        log::error!("Received Close Frame! Closing stream.");
+                        try_to_close_connection(&mut write, frame.clone()).await;
+                        return RpcError::WsClosed(frame);
+                    },
+                    Some(Ok(_)) => {},
+                    Some(Err(e)) => return e.into(),
+                    None => return RpcError::WsClosed(None),
+                }
+            },
+
+            // consumer dropped the handle
+            _ = tx.closed() => {
+                try_to_close_connection(&mut write, None).await;
+                return RpcError::WsClosed(None);
+            },
+
+            // fails right away if the target is fixed (sender is gone), which only disables the branch
+            Ok(()) = target_rx.changed() => {
+                try_to_close_connection(&mut write, None).await;
+                return RpcError::EndpointUnhealthy(target.ws_url);
+            },
+
+            action = keepalive.tick() => {
+                match action {
+                    KeepaliveAction::Ping => {
+                        if let Err(e) = write.send(Message::Ping(vec![].into())).await {
+                            log::error!("Failed to send a Heartbeat Ping! {e}");
+                        }
+                    },
+                    // no Close Frame, the peer is most likely gone anyway
+                    KeepaliveAction::Dead(e) => return e,
+                }
+            }
+        }
+    }
+}
+
+/// `Err` => the consumer dropped the handle.
+#[allow(dead_code)]
+async fn deliver(
+    state: &mut SessionState,
+    tx: &mpsc::Sender<Result<AccountNotification, RpcError>>,
+    notification: Result<AccountNotification, RpcError>
+) -> Result<(), ()> {
+    if let Ok(notification) = &notification {
+        if let Some(last) = state.last.as_ref().filter(|last| notification.slot < last.slot) {
+            log::debug!("Dropping notification of slot {}, slot {} was already delivered", notification.slot, last.slot);
+            return Ok(());
+        }
+        state.last = Some(notification.clone());
+    }
+    tx.send(notification).await.map_err(|_| ())
+}
+
+/// ### Current state, if it differs from the last delivered one
+/// `minContextSlot` makes a lagging node answer -32016 instead of an even older state, that is retried by the client's
+/// retry policy. Failures are only logged, the subscription goes on without the catch-up.
+#[allow(dead_code)]
+async fn catch_up(client: &RpcClient, account: &SupervisedAccount, last: Option<&AccountNotification>) -> Option<AccountNotification> {
+    let config: AccountInfoConfig = AccountInfoConfig {
+        min_context_slot: last.map(|last| last.slot).or(account.config.min_context_slot),
+        ..account.config.clone()
+    };
+
+    match client.get_account_info_with_context(&account.pubkey, config).await {
+        Ok(RpcContextResponse { context, value: Some(value) }) => {
+            let fetched: AccountNotification = AccountNotification { slot: context.slot, account: value, resync: true };
+            match last {
+                Some(last) if last.content_hash() == fetched.content_hash() => None,
+                _ => Some(fetched),
+            }
+        },
+        // a closed account has no accountNotification form in an arbitrary encoding, the next update (if any) tells
+        Ok(RpcContextResponse { value: None, .. }) => {
+            log::warn!("Resync: account {} doesn't exist (anymore)", account.pubkey);
+            None
+        },
+        Err(e) => {
+            log::warn!("Resync of {} failed: {e}", account.pubkey);
+            None
+        },
+    }
+}
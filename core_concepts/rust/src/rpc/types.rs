@@ -0,0 +1,455 @@
+use serde::Deserialize;
+
+use super::{
+    communication::CommitmentLevel,
+    encoding::{UiEncoding, decode_binary, decode_transaction},
+    error::RpcError,
+};
+
+/// ### JSON-RPC 2.0 response envelope
+/// Exactly one of `result` / `error` is present in a valid response.
+/// `result` itself may be `null` (e.g. getTransaction for unknown signature), that's why it's wrapped in Option.
+#[derive(Deserialize, Debug)]
+pub struct RpcResponse<T> {
+    #[allow(dead_code)]
+    pub jsonrpc: String,
+    pub id: Option<u64>,  // `null` if the node couldn't even parse the request
+    pub result: Option<T>,
+    pub error: Option<RpcResponseError>,
+}
+
+/// ### JSON-RPC error object
+/// Returned by the node instead of `result`, e.g. `{"code": -32602, "message": "Invalid param: ..."}`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcResponseError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<serde_json::Value>,
+}
+
+impl std::fmt::Display for RpcResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for RpcResponseError {}
+
+/// ### getTransaction result
+/// Mirrors `EncodedConfirmedTransactionWithStatusMeta` from solana-transaction-status,
+/// but only with the fields we actually use.
+/// Any encoding except jsonParsed (its instructions have no `programIdIndex`) fits, see `EncodedTransaction::decode`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodedTransactionWithMeta {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub version: Option<TransactionVersion>,
+    pub meta: Option<TransactionMeta>,
+    pub transaction: EncodedTransaction,
+}
+
+/// ### Transaction as it came over the wire
+/// Binary encodings come as `["<data>", "<encoding>"]`, the deprecated "binary" one as a plain base58 string.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EncodedTransaction {
+    Binary(String, UiEncoding),
+    LegacyBinary(String),
+    Json(UiTransaction),
+}
+
+#[allow(dead_code)]
+impl EncodedTransaction {
+    /// Same `UiTransaction` no matter which encoding was requested.
+    pub fn decode(&self) -> Result<UiTransaction, RpcError> {
+        match self {
+            Self::Binary(data, encoding) => decode_transaction(&decode_binary(data, *encoding)?),
+            Self::LegacyBinary(data) => decode_transaction(&decode_binary(data, UiEncoding::Base58)?),
+            Self::Json(tx) => Ok(tx.clone()),
+        }
+    }
+}
+
+/// `"legacy"` or a version number (currently only `0`)
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum TransactionVersion {
+    Number(u8),
+    Legacy(String),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionMeta {
+    pub err: Option<serde_json::Value>,  // TransactionError has dozens of variants, keeping it raw
+    pub fee: u64,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    pub inner_instructions: Option<Vec<InnerInstructions>>,
+    pub log_messages: Option<Vec<String>>,
+    pub pre_token_balances: Option<Vec<TokenBalance>>,
+    pub post_token_balances: Option<Vec<TokenBalance>>,
+    pub loaded_addresses: Option<LoadedAddresses>,
+    pub compute_units_consumed: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct InnerInstructions {
+    pub index: u8,  // index of the top-level instruction, that produced these CPIs
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+    pub account_index: u8,
+    pub mint: String,
+    pub owner: Option<String>,
+    pub program_id: Option<String>,
+    pub ui_token_amount: UiTokenAmount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiTokenAmount {
+    pub amount: String,
+    pub decimals: u8,
+    pub ui_amount_string: String,
+}
+
+/// Addresses loaded from Address Lookup Tables (v0 transactions only).
+/// Full account list of the tx == accountKeys ++ writable ++ readonly
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LoadedAddresses {
+    pub writable: Vec<String>,
+    pub readonly: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct UiTransaction {
+    pub signatures: Vec<String>,
+    pub message: UiMessage,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiMessage {
+    pub header: MessageHeader,
+    pub account_keys: Vec<String>,
+    pub recent_blockhash: String,
+    pub instructions: Vec<CompiledInstruction>,
+    pub address_table_lookups: Option<Vec<AddressTableLookup>>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageHeader {
+    pub num_required_signatures: u8,
+    pub num_readonly_signed_accounts: u8,
+    pub num_readonly_unsigned_accounts: u8,
+}
+
+/// Accounts are referenced by index into `accountKeys` (++ loaded addresses), data is base58 encoded.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompiledInstruction {
+    pub program_id_index: u8,
+    pub accounts: Vec<u8>,
+    pub data: String,
+    pub stack_height: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTableLookup {
+    pub account_key: String,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// ### Wrapper for methods, that return `{ context, value }`
+/// e.g. getAccountInfo, getBalance, getLatestBlockhash
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcContextResponse<T> {
+    pub context: RpcContext,
+    pub value: T,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcContext {
+    pub slot: u64,
+    pub api_version: Option<String>,
+}
+
+/// ### getAccountInfo value
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiAccount {
+    pub lamports: u64,
+    pub owner: String,
+    pub data: UiAccountData,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub space: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl UiAccount {
+    /// Same account with raw data bytes, see `UiAccountData::decode`.
+    pub fn decode(&self) -> Result<Account, RpcError> {
+        Ok(Account {
+            lamports: self.lamports,
+            owner: self.owner.clone(),
+            data: self.data.decode()?,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+        })
+    }
+}
+
+/// Binary encodings come as `["<data>", "<encoding>"]`, the deprecated "binary" one as a plain base58 string,
+/// "jsonParsed" comes as an object (or falls back to binary, if the node doesn't know how to parse the account).
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum UiAccountData {
+    Binary(String, UiEncoding),
+    LegacyBinary(String),
+    Json(serde_json::Value),
+}
+
+#[allow(dead_code)]
+impl UiAccountData {
+    /// Raw account data, zstd is decompressed. Parsed JSON can't be turned back into bytes, request a binary encoding instead.
+    pub fn decode(&self) -> Result<Vec<u8>, RpcError> {
+        match self {
+            Self::Binary(data, encoding) => decode_binary(data, *encoding),
+            Self::LegacyBinary(data) => decode_binary(data, UiEncoding::Base58),
+            Self::Json(_) => Err(RpcError::Decode("Account data is jsonParsed, raw bytes are not available".into())),
+        }
+    }
+}
+
+/// ### Account with decoded data
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    pub lamports: u64,
+    pub owner: String,
+    pub data: Vec<u8>,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestBlockhash {
+    pub blockhash: String,
+    pub last_valid_block_height: u64,
+}
+
+/// ### getSignatureStatuses item
+/// `confirmations == None` => the block is rooted (finalized).
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatus {
+    pub slot: u64,
+    pub confirmations: Option<usize>,
+    pub err: Option<serde_json::Value>,
+    pub confirmation_status: Option<CommitmentLevel>,
+}
+
+/// ### getRecentPrioritizationFees item
+/// Lowest fee (micro-lamports per CU) paid to land in `slot` by txs that write-lock ALL of the requested accounts.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrioritizationFee {
+    pub slot: u64,
+    pub prioritization_fee: u64,
+}
+
+/// ### simulateTransaction value
+/// `err` is the same TransactionError, that `getTransaction` would report.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateTransactionResult {
+    pub err: Option<serde_json::Value>,
+    pub logs: Option<Vec<String>>,
+    pub units_consumed: Option<u64>,  // missing on old nodes
+}
+
+/// ### getSignaturesForAddress item
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub err: Option<serde_json::Value>,
+    pub memo: Option<String>,
+    pub block_time: Option<i64>,
+    pub confirmation_status: Option<CommitmentLevel>,
+}
+
+/// ### PubSub notification envelope
+/// `{"jsonrpc":"2.0","method":"accountNotification","params":{"result":{...},"subscription":23784}}`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcNotification<T> {
+    pub method: String,
+    pub params: RpcNotificationParams<T>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcNotificationParams<T> {
+    pub result: T,
+    pub subscription: u64,
+}
+
+/// ### accountNotification result
+/// The node sends `{ context: { slot }, value: <account> }`, it's flattened into the slot + account pair.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "RpcContextResponse<UiAccount>")]
+pub struct AccountNotification {
+    pub slot: u64,
+    pub account: UiAccount,  // lamports, owner, data, executable, rentEpoch
+    /// true => not pushed by the node, but fetched via getAccountInfo after a reconnect (see `account_subscribe_supervised`),
+    /// it covers the updates missed while the socket was down
+    pub resync: bool,
+}
+
+impl From<RpcContextResponse<UiAccount>> for AccountNotification {
+    fn from(res: RpcContextResponse<UiAccount>) -> Self {
+        Self { slot: res.context.slot, account: res.value, resync: false }
+    }
+}
+
+/// ### logsNotification result
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "RpcContextResponse<RpcLogs>")]
+pub struct LogsNotification {
+    pub slot: u64,
+    pub signature: String,
+    pub err: Option<serde_json::Value>,
+    pub logs: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcLogs {
+    pub signature: String,
+    pub err: Option<serde_json::Value>,
+    pub logs: Vec<String>,
+}
+
+impl From<RpcContextResponse<RpcLogs>> for LogsNotification {
+    fn from(res: RpcContextResponse<RpcLogs>) -> Self {
+        Self { slot: res.context.slot, signature: res.value.signature, err: res.value.err, logs: res.value.logs }
+    }
+}
+
+/// ### programNotification result
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "RpcContextResponse<RpcKeyedAccount>")]
+pub struct ProgramNotification {
+    pub slot: u64,
+    pub pubkey: String,
+    pub account: UiAccount,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcKeyedAccount {
+    pub pubkey: String,
+    pub account: UiAccount,
+}
+
+impl From<RpcContextResponse<RpcKeyedAccount>> for ProgramNotification {
+    fn from(res: RpcContextResponse<RpcKeyedAccount>) -> Self {
+        Self { slot: res.context.slot, pubkey: res.value.pubkey, account: res.value.account }
+    }
+}
+
+/// ### signatureNotification result
+/// `err == None` => transaction succeeded.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "RpcContextResponse<RpcSignatureResult>")]
+pub struct SignatureNotification {
+    pub slot: u64,
+    pub err: Option<serde_json::Value>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcSignatureResult {
+    pub err: Option<serde_json::Value>,
+}
+
+impl From<RpcContextResponse<RpcSignatureResult>> for SignatureNotification {
+    fn from(res: RpcContextResponse<RpcSignatureResult>) -> Self {
+        Self { slot: res.context.slot, err: res.value.err }
+    }
+}
+
+/// ### slotNotification result
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SlotInfo {
+    pub parent: u64,
+    pub root: u64,
+    pub slot: u64,
+}
+
+/// ### blockNotification result
+/// `block` is None if `err` is set (e.g. the slot was skipped).
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "RpcContextResponse<RpcBlockUpdate>")]
+pub struct BlockNotification {
+    pub slot: u64,
+    pub err: Option<serde_json::Value>,
+    pub block: Option<UiConfirmedBlock>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcBlockUpdate {
+    pub slot: u64,
+    pub block: Option<UiConfirmedBlock>,
+    pub err: Option<serde_json::Value>,
+}
+
+impl From<RpcContextResponse<RpcBlockUpdate>> for BlockNotification {
+    fn from(res: RpcContextResponse<RpcBlockUpdate>) -> Self {
+        Self { slot: res.value.slot, err: res.value.err, block: res.value.block }
+    }
+}
+
+/// `transactions` / `signatures` are present depending on the requested `transactionDetails`.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiConfirmedBlock {
+    pub blockhash: String,
+    pub previous_blockhash: String,
+    pub parent_slot: u64,
+    pub block_time: Option<i64>,
+    pub block_height: Option<u64>,
+    pub transactions: Option<Vec<BlockTransaction>>,
+    pub signatures: Option<Vec<String>>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockTransaction {
+    pub transaction: EncodedTransaction,
+    pub meta: Option<TransactionMeta>,
+    pub version: Option<TransactionVersion>,
+}